@@ -0,0 +1,99 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+lazy_static::lazy_static! {
+    pub(crate) static ref ARGS: Args = Args::parse(env::args().skip(1));
+}
+
+/// 设置工程根目录的环境变量。
+const ROOT_ENV: &str = "RUSTENG_ROOT";
+
+/// 命令行参数，带值的参数支持 `--root <dir>` 与 `--root=<dir>` 两种写法。
+/// `--dev` 开启开发模式，`--control <port>` 在本机端口上开启控制服务，
+/// `--record <file>` / `--replay <file>` 录制或回放玩家输入。
+#[derive(Debug, Default)]
+pub(crate) struct Args {
+    root: Option<PathBuf>,
+    dev: bool,
+    control: Option<u16>,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Args {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            if let Some(root) = flag_value(&arg, "--root", &mut args) {
+                parsed.root = root.map(PathBuf::from);
+            } else if arg == "--dev" {
+                parsed.dev = true;
+            } else if let Some(port) = flag_value(&arg, "--control", &mut args) {
+                parsed.control = port.and_then(|port| parse_port(&port));
+            } else if let Some(path) = flag_value(&arg, "--record", &mut args) {
+                parsed.record = path.map(PathBuf::from);
+            } else if let Some(path) = flag_value(&arg, "--replay", &mut args) {
+                parsed.replay = path.map(PathBuf::from);
+            } else {
+                log::warn!("unknown argument `{arg}`");
+            }
+        }
+        parsed
+    }
+
+    /// 开发模式下会监视剧本和立绘目录并热重载。
+    pub(crate) fn dev(&self) -> bool {
+        self.dev
+    }
+
+    /// 控制服务监听的端口，只绑定 127.0.0.1。
+    pub(crate) fn control(&self) -> Option<u16> {
+        self.control
+    }
+
+    /// 录制玩家输入和引擎事件的文件。
+    pub(crate) fn record(&self) -> Option<&Path> {
+        self.record.as_deref()
+    }
+
+    /// 要回放的录制文件，回放完毕后自动退出。
+    pub(crate) fn replay(&self) -> Option<&Path> {
+        self.replay.as_deref()
+    }
+
+    /// 工程根目录：命令行参数 > 环境变量 > 可执行文件所在目录。
+    /// 可执行文件旁没有 source/ini.toml 时（如 cargo run）退回当前目录。
+    pub(crate) fn root(&self) -> PathBuf {
+        if let Some(root) = &self.root {
+            return root.clone();
+        }
+        if let Some(root) = env::var_os(ROOT_ENV).filter(|root| !root.is_empty()) {
+            return PathBuf::from(root);
+        }
+        env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .filter(|dir| dir.join("source/ini.toml").is_file())
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+/// `--name value` 或 `--name=value` 的值，不是这个参数时返回 None。
+fn flag_value(
+    arg: &str,
+    name: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Option<Option<String>> {
+    if arg == name {
+        return Some(args.next());
+    }
+    arg.strip_prefix(name)?
+        .strip_prefix('=')
+        .map(|value| Some(value.to_string()))
+}
+
+fn parse_port(port: &str) -> Option<u16> {
+    port.parse()
+        .inspect_err(|_| log::warn!("invalid control port `{port}`"))
+        .ok()
+}
@@ -1,13 +1,21 @@
-use crate::config::ENGINE_CONFIG;
-use crate::error::{EngineError, ExecutorError};
+use crate::config::{parse_config, read_config, ENGINE_CONFIG};
+use crate::error::{ConfigError, EngineError, ExecutorError};
+use crate::media::placeholder::load_image;
 use crate::ui::initialize::ExItem;
 use serde::{Deserialize, Serialize};
-use slint::{Image, ModelRc, VecModel};
-use std::{cell::RefCell, path::Path, rc::Rc};
-use std::{collections::HashMap, fs};
+use slint::{ModelRc, VecModel};
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
 
 lazy_static::lazy_static! {
-    pub(crate) static ref CG_CONFIG: CgConfig = load_cg();
+    static ref LOADED_CG_CONFIG: Result<CgConfig, ConfigError> = load_cg();
+    pub(crate) static ref CG_CONFIG: &'static CgConfig = LOADED_CG_CONFIG
+        .as_ref()
+        .expect("cg config is checked by load_all at startup");
+}
+
+pub(crate) fn check() -> Result<(), ConfigError> {
+    LOADED_CG_CONFIG.as_ref().map(|_| ()).map_err(Clone::clone)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -58,12 +66,12 @@ impl CgConfig {
     }
 }
 
-fn load_cg() -> CgConfig {
-    let content = fs::read_to_string(format!("{}length.toml", ENGINE_CONFIG.cg_path(),)).unwrap();
-    let name_item: LengthWrapper = toml::from_str(&content).unwrap();
+fn load_cg() -> Result<CgConfig, ConfigError> {
+    let path = format!("{}length.toml", ENGINE_CONFIG.cg_path());
+    let name_item: LengthWrapper = parse_config(&path, &read_config(&path)?)?;
     let index_item = name_item.cast.clone();
     let length = index_item.len();
-    CgConfig {
+    Ok(CgConfig {
         cg_by_name: name_item
             .cast
             .into_iter()
@@ -74,7 +82,7 @@ fn load_cg() -> CgConfig {
             .map(|length| (length.index, (length.name, length.length)))
             .collect(),
         length,
-    }
+    })
 }
 
 pub(crate) fn get_cg(cg: Rc<RefCell<Vec<u64>>>) -> Result<ModelRc<ModelRc<ExItem>>, EngineError> {
@@ -90,14 +98,11 @@ pub(crate) fn get_cg(cg: Rc<RefCell<Vec<u64>>>) -> Result<ModelRc<ModelRc<ExItem
                 for j in 1..=*length {
                     if cgs & (1 << (j + i % 64 - 1)) != 0 {
                         if let Some((name, _)) = CG_CONFIG.find_by_id(j + i - 1) {
-                            images.push(
-                                Image::load_from_path(Path::new(&format!(
-                                    "{}{}.png",
-                                    ENGINE_CONFIG.cg_path(),
-                                    name
-                                )))
-                                .unwrap(),
-                            );
+                            images.push(load_image(&format!(
+                                "{}{}.png",
+                                ENGINE_CONFIG.cg_path(),
+                                name
+                            )));
                             is_lock = false;
                         } else {
                             return Err(ExecutorError::CgMetadataMissing(j + i - 1).into());
@@ -1,5 +1,5 @@
 use crate::config::cg::CG_CONFIG;
-use crate::config::{cg::CgMap, ENGINE_CONFIG};
+use crate::config::{cg::CgMap, parse_config, read::ReadMap, ENGINE_CONFIG};
 use crate::error::{EngineError, SaveError};
 use crate::executors::executor::Executor;
 use serde::{Deserialize, Serialize};
@@ -10,6 +10,9 @@ use std::rc::Rc;
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct ExtraConfig {
     cg: CgMap,
+    // 旧 extra.toml 没有已读记录
+    #[serde(default)]
+    read: ReadMap,
 }
 
 impl ExtraConfig {
@@ -19,28 +22,36 @@ impl ExtraConfig {
 }
 
 impl Executor {
-    pub(crate) fn load_extra(&mut self) {
-        let extra_config = load_extra_config();
+    pub(crate) fn load_extra(&mut self) -> Result<(), EngineError> {
+        let extra_config = load_extra_config()?;
+        self.set_read(extra_config.read.clone());
         self.set_cg(extra_config.cg());
+        Ok(())
     }
 }
 
-fn load_extra_config() -> ExtraConfig {
-    if let Ok(content) = fs::read_to_string(format!("{}/extra.toml", ENGINE_CONFIG.save_path())) {
-        toml::from_str(&content).unwrap()
+fn load_extra_config() -> Result<ExtraConfig, EngineError> {
+    let path = format!("{}/extra.toml", ENGINE_CONFIG.save_path());
+    if let Ok(content) = fs::read_to_string(&path) {
+        Ok(parse_config(&path, &content)?)
     } else {
         let num = CG_CONFIG.length() / 64 + 1;
-        ExtraConfig {
+        Ok(ExtraConfig {
             cg: CgMap::new(vec![0; num]),
-        }
+            read: ReadMap::default(),
+        })
     }
 }
 
-pub(crate) fn save_extra_config(cg: Rc<RefCell<Vec<u64>>>) -> Result<(), EngineError> {
+pub(crate) fn save_extra_config(
+    cg: Rc<RefCell<Vec<u64>>>,
+    read: Rc<RefCell<ReadMap>>,
+) -> Result<(), EngineError> {
     let cg = cg.borrow();
     let path = format!("{}/extra.toml", ENGINE_CONFIG.save_path());
     let content = toml::to_string(&ExtraConfig {
         cg: CgMap::new(cg.clone()),
+        read: read.borrow().clone(),
     })
     .map_err(SaveError::from)?;
     fs::write(&path, content).map_err(|e| SaveError::Write { path, source: e })?;
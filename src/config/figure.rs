@@ -1,9 +1,39 @@
-use crate::config::ENGINE_CONFIG;
+use crate::config::{parse_config, read_config, ENGINE_CONFIG};
+use crate::error::ConfigError;
+use crate::parser::markup::parse_color;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs};
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
 
 lazy_static::lazy_static! {
-    pub(crate) static ref FIGURE_CONFIG: FigureConfig = load_figure();
+    static ref LOADED_FIGURE_CONFIG: Result<FigureConfig, ConfigError> = load_figure();
+    // 开发模式下会重新加载，所以放在锁里
+    static ref FIGURE_CONFIG: RwLock<FigureConfig> = RwLock::new(
+        LOADED_FIGURE_CONFIG
+            .as_ref()
+            .expect("figure config is checked by load_all at startup")
+            .clone(),
+    );
+}
+
+pub(crate) fn figure_config() -> RwLockReadGuard<'static, FigureConfig> {
+    FIGURE_CONFIG.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 重新读取所有立绘配置，出错时保留原来的配置。
+pub(crate) fn reload() -> Result<(), ConfigError> {
+    let config = load_figure()?;
+    *FIGURE_CONFIG
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = config;
+    Ok(())
+}
+
+pub(crate) fn check() -> Result<(), ConfigError> {
+    LOADED_FIGURE_CONFIG
+        .as_ref()
+        .map(|_| ())
+        .map_err(Clone::clone)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -11,6 +41,9 @@ struct Face {
     name: String,
     x: f32,
     y: f32,
+    // 口型图层：闭嘴、半张、张嘴
+    #[serde(default)]
+    mouth: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -34,11 +67,27 @@ struct Offset {
     offset: f32,
 }
 
+/// 说话时的名牌颜色、台词颜色、显示名和侧边头像，整节可省略。
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Display {
+    pub(crate) name_color: Option<String>,
+    pub(crate) text_color: Option<String>,
+    /// 代替台词里说话人显示的名字，设置了 `reveal_flag` 时只在该变量设置前生效。
+    pub(crate) display_name: Option<String>,
+    pub(crate) reveal_flag: Option<String>,
+    pub(crate) portrait: bool,
+    /// 剧本里用作说话人的其他写法，比如只写名字不写姓。
+    pub(crate) aliases: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct FigureRead {
     body: BodyWrapper,
     face: FaceWrapper,
     offset: Offset,
+    #[serde(default)]
+    display: Display,
 }
 
 type FigureConfigRef<'a> = (
@@ -47,11 +96,13 @@ type FigureConfigRef<'a> = (
     Option<&'a f32>,
 );
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub(crate) struct FigureConfig {
     body_list: HashMap<String, HashMap<String, f32>>,
     face_list: HashMap<String, HashMap<String, (f32, f32)>>,
     offset_list: HashMap<String, f32>,
+    display_list: HashMap<String, Display>,
+    mouth_list: HashMap<String, HashMap<String, [String; 3]>>,
 }
 
 impl FigureConfig {
@@ -62,21 +113,57 @@ impl FigureConfig {
             self.offset_list.get(name),
         )
     }
+
+    /// 表情的口型图层，没有配置时为 None。
+    pub(crate) fn mouths(&self, name: &str, face: &str) -> Option<&[String; 3]> {
+        self.mouth_list.get(name)?.get(face)
+    }
+
+    /// 台词说话人对应的角色名：角色名、全名，或 `[display]` 里 `aliases` 列出的别名。
+    pub(crate) fn character_of<'a>(&'a self, speaker: &str) -> Option<&'a str> {
+        if speaker.is_empty() {
+            return None;
+        }
+        if let Some(name) = ENGINE_CONFIG.character_of(speaker) {
+            return Some(name);
+        }
+        self.display_list
+            .iter()
+            .filter(|(_, display)| display.aliases.iter().any(|alias| alias == speaker))
+            .map(|(name, _)| name.as_str())
+            .min()
+    }
+
+    pub(crate) fn display(&self, name: &str) -> Option<&Display> {
+        self.display_list.get(name)
+    }
 }
 
-fn load_figure() -> FigureConfig {
+fn load_figure() -> Result<FigureConfig, ConfigError> {
     let mut body_list = HashMap::new();
     let mut face_list = HashMap::new();
     let mut offset_list = HashMap::new();
+    let mut display_list = HashMap::new();
+    let mut mouth_list = HashMap::new();
     for char in &ENGINE_CONFIG.character_name_list() {
-        let content = fs::read_to_string(format!(
-            "{}{}/{}.toml",
-            ENGINE_CONFIG.figure_path(),
-            char,
-            char
-        ))
-        .unwrap();
-        let item: FigureRead = toml::from_str(&content).unwrap();
+        let path = format!("{}{}/{}.toml", ENGINE_CONFIG.figure_path(), char, char);
+        let item: FigureRead = parse_config(&path, &read_config(&path)?)?;
+        let mut mouths = HashMap::new();
+        for face in &item.face.cast {
+            if face.mouth.is_empty() {
+                continue;
+            }
+            let Ok(layers) = <[String; 3]>::try_from(face.mouth.clone()) else {
+                return Err(ConfigError::Parse {
+                    path,
+                    key: Some(format!("face.{}.mouth", face.name)),
+                    line: None,
+                    message: "expected three mouth layers: closed, half and open".to_string(),
+                });
+            };
+            mouths.insert(face.name.clone(), layers);
+        }
+        mouth_list.insert(char.to_string(), mouths);
         face_list.insert(
             char.to_string(),
             item.face
@@ -94,11 +181,29 @@ fn load_figure() -> FigureConfig {
                 .collect(),
         );
         offset_list.insert(char.to_string(), item.offset.offset);
+        for (key, color) in [
+            ("name_color", &item.display.name_color),
+            ("text_color", &item.display.text_color),
+        ] {
+            if let Some(color) = color.as_ref().filter(|c| parse_color(c).is_none()) {
+                return Err(ConfigError::Parse {
+                    path,
+                    key: Some(format!("display.{key}")),
+                    line: None,
+                    message: format!(
+                        "invalid color `{color}`, expected #rgb, #rrggbb or #rrggbbaa"
+                    ),
+                });
+            }
+        }
+        display_list.insert(char.to_string(), item.display);
     }
 
-    FigureConfig {
+    Ok(FigureConfig {
         body_list,
         face_list,
         offset_list,
-    }
+        display_list,
+        mouth_list,
+    })
 }
@@ -1,5 +1,8 @@
-use fontdb::Database;
-use std::collections::BTreeSet;
+use fontdb::{Database, Family, Query, Weight};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Mutex, PoisonError},
+};
 
 lazy_static::lazy_static! {
     pub(crate) static ref SYSTEM_FONTS: SystemFonts = SystemFonts::load();
@@ -7,6 +10,9 @@ lazy_static::lazy_static! {
 
 pub(crate) struct SystemFonts {
     families: Vec<String>,
+    db: Database,
+    // 按 (字体, 是否粗体) 缓存的字宽，以字号为 1
+    advances: Mutex<HashMap<(String, bool), HashMap<char, f32>>>,
 }
 
 impl SystemFonts {
@@ -26,6 +32,8 @@ impl SystemFonts {
 
         SystemFonts {
             families: set.into_iter().collect(),
+            db,
+            advances: Mutex::new(HashMap::new()),
         }
     }
 
@@ -33,6 +41,53 @@ impl SystemFonts {
         &self.families
     }
 
+    /// 各字符的宽度（以字号为 1）。字体里没有的字按全角 1、半角 0.5 估算。
+    pub(crate) fn measure(&self, family: &str, bold: bool, chars: &[char]) -> Vec<f32> {
+        let mut advances = self.advances.lock().unwrap_or_else(PoisonError::into_inner);
+        let widths = advances.entry((family.to_string(), bold)).or_default();
+
+        let mut missing: Vec<char> = chars
+            .iter()
+            .filter(|c| !widths.contains_key(c))
+            .copied()
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        if !missing.is_empty() {
+            let query = Query {
+                families: &[Family::Name(family)],
+                weight: if bold { Weight::BOLD } else { Weight::NORMAL },
+                ..Query::default()
+            };
+            // 字体文件只在有新字符时读一次
+            let measured = self.db.query(&query).and_then(|id| {
+                self.db.with_face_data(id, |data, index| {
+                    let face = ttf_parser::Face::parse(data, index).ok()?;
+                    let units = f32::from(face.units_per_em());
+                    Some(
+                        missing
+                            .iter()
+                            .map(|c| {
+                                face.glyph_index(*c)
+                                    .and_then(|glyph| face.glyph_hor_advance(glyph))
+                                    .map(|advance| f32::from(advance) / units)
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })?
+            });
+            for (i, c) in missing.iter().enumerate() {
+                let width = measured
+                    .as_ref()
+                    .and_then(|measured| measured[i])
+                    .unwrap_or(if is_wide(*c) { 1.0 } else { 0.5 });
+                widths.insert(*c, width);
+            }
+        }
+
+        chars.iter().map(|c| widths[c]).collect()
+    }
+
     fn contains(&self, name: &str) -> bool {
         self.families.iter().any(|f| f == name)
     }
@@ -77,3 +132,19 @@ fn supports_chinese(db: &Database, id: fontdb::ID) -> bool {
     })
     .unwrap_or(false)
 }
+
+/// 中日韩文字和全角符号，这些字前后都可以换行。
+pub(crate) fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3040..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x20000..=0x3FFFD
+    )
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct InitializeConfig {
@@ -14,6 +15,172 @@ pub(crate) struct InitializeConfig {
     pub(crate) save_path: String,
 }
 
+impl InitializeConfig {
+    /// 相对路径都以工程根目录为基准，绝对路径保持不变。
+    pub(crate) fn resolve(&mut self, root: &Path) {
+        for path in [
+            &mut self.script_path,
+            &mut self.background_path,
+            &mut self.cg_path,
+            &mut self.voice_path,
+            &mut self.bgm_path,
+            &mut self.figure_path,
+            &mut self.video_path,
+            &mut self.save_path,
+        ] {
+            *path = root.join(path.as_str()).to_string_lossy().into_owned();
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct SaveConfig {
+    pub(crate) pages: usize,
+    pub(crate) slots: usize,
+    pub(crate) confirm_overwrite: bool,
+    // 每个存档最多保存的履历条数
+    pub(crate) backlog_limit: usize,
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        SaveConfig {
+            pages: 10,
+            slots: 16,
+            confirm_overwrite: true,
+            backlog_limit: 200,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct RollbackConfig {
+    pub(crate) limit: usize,
+    pub(crate) allow_rechoose: bool,
+}
+
+impl Default for RollbackConfig {
+    fn default() -> Self {
+        RollbackConfig {
+            limit: 100,
+            allow_rechoose: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct BacklogConfig {
+    pub(crate) page_size: usize,
+}
+
+impl Default for BacklogConfig {
+    fn default() -> Self {
+        BacklogConfig { page_size: 4 }
+    }
+}
+
+/// 台词超出对话框行数时的处理：分页显示或缩小字号。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Overflow {
+    Page,
+    Shrink,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct DialogueConfig {
+    pub(crate) max_lines: usize,
+    pub(crate) overflow: Overflow,
+}
+
+impl Default for DialogueConfig {
+    fn default() -> Self {
+        DialogueConfig {
+            max_lines: 3,
+            overflow: Overflow::Page,
+        }
+    }
+}
+
+/// 配音设置，auto 开启时没有 @voice 的台词按台词 ID 自动查找配音。
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct VoiceConfig {
+    pub(crate) auto: bool,
+}
+
+/// 日志设置，环境变量 RUSTENG_LOG 优先于 level。
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct LogConfig {
+    pub(crate) level: String,
+    pub(crate) max_size_kb: u64,
+    pub(crate) files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "info".to_string(),
+            max_size_kb: 1024,
+            files: 3,
+        }
+    }
+}
+
+/// 标题画面之前依次播放的内容，图片可点击跳过，播完自动进入下一项。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum PreTitle {
+    Video {
+        video: String,
+    },
+    Image {
+        image: String,
+        #[serde(default = "default_pre_title_duration")]
+        duration: f32,
+    },
+}
+
+fn default_pre_title_duration() -> f32 {
+    3.0
+}
+
+fn default_entry_script() -> String {
+    "ky01".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct TitleConfig {
+    #[serde(default = "default_entry_script")]
+    pub(crate) entry_script: String,
+    // 为空时从剧本开头开始
+    #[serde(default)]
+    pub(crate) entry_label: String,
+    #[serde(default)]
+    pub(crate) bgm: String,
+    #[serde(default)]
+    pub(crate) background: String,
+    #[serde(default)]
+    pub(crate) pre_title: Vec<PreTitle>,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        TitleConfig {
+            entry_script: default_entry_script(),
+            entry_label: String::new(),
+            bgm: String::new(),
+            background: String::new(),
+            pre_title: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct Character(HashMap<String, String>);
 
@@ -29,4 +196,16 @@ impl Character {
     pub(crate) fn full_name_list(&self) -> HashSet<&String> {
         self.0.values().collect()
     }
+
+    /// 台词说话人对应的角色名，说话人须与角色名或全名完全一致。
+    pub(crate) fn find(&self, speaker: &str) -> Option<&String> {
+        if let Some((name, _)) = self.0.get_key_value(speaker) {
+            return Some(name);
+        }
+        self.0
+            .iter()
+            .filter(|(_, full_name)| *full_name == speaker)
+            .map(|(name, _)| name)
+            .min()
+    }
 }
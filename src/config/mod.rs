@@ -1,10 +1,17 @@
-use crate::config::initialize::{Character, InitializeConfig};
-use serde::{Deserialize, Serialize};
+use crate::config::args::ARGS;
+use crate::config::initialize::{
+    BacklogConfig, Character, DialogueConfig, InitializeConfig, LogConfig, Overflow, PreTitle,
+    RollbackConfig, SaveConfig, TitleConfig, VoiceConfig,
+};
+use crate::error::ConfigError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     fs,
+    sync::Arc,
 };
 
+pub(crate) mod args;
 pub(crate) mod figure;
 pub(crate) mod initialize;
 pub(crate) mod save_load;
@@ -13,19 +20,40 @@ pub(crate) mod cg;
 pub(crate) mod character_volume;
 pub(crate) mod extra;
 pub(crate) mod font;
+pub(crate) mod read;
 pub(crate) mod system;
 pub(crate) mod text;
+pub(crate) mod textbox;
+pub(crate) mod transcript;
 pub(crate) mod user;
 pub(crate) mod voice;
 pub(crate) mod volume;
 
 lazy_static::lazy_static! {
-    pub(crate) static ref ENGINE_CONFIG: EngineConfig = load_engine_config();
+    static ref LOADED_ENGINE_CONFIG: Result<EngineConfig, ConfigError> = load_engine_config();
+    // 启动时已经由 load_all 检查过，这里不会失败
+    pub(crate) static ref ENGINE_CONFIG: &'static EngineConfig = LOADED_ENGINE_CONFIG
+        .as_ref()
+        .expect("engine config is checked by load_all at startup");
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct EngineConfig {
     initialize: InitializeConfig,
+    #[serde(default)]
+    save: SaveConfig,
+    #[serde(default)]
+    rollback: RollbackConfig,
+    #[serde(default)]
+    backlog: BacklogConfig,
+    #[serde(default)]
+    dialogue: DialogueConfig,
+    #[serde(default)]
+    voice: VoiceConfig,
+    #[serde(default)]
+    title: TitleConfig,
+    #[serde(default)]
+    log: LogConfig,
     character: Character,
 }
 
@@ -66,6 +94,78 @@ impl EngineConfig {
         &self.initialize.save_path
     }
 
+    pub(crate) fn save_pages(&self) -> usize {
+        self.save.pages
+    }
+
+    pub(crate) fn save_slots(&self) -> usize {
+        self.save.slots
+    }
+
+    pub(crate) fn confirm_overwrite(&self) -> bool {
+        self.save.confirm_overwrite
+    }
+
+    pub(crate) fn backlog_limit(&self) -> usize {
+        self.save.backlog_limit
+    }
+
+    pub(crate) fn rollback_limit(&self) -> usize {
+        self.rollback.limit
+    }
+
+    pub(crate) fn allow_rechoose(&self) -> bool {
+        self.rollback.allow_rechoose
+    }
+
+    pub(crate) fn backlog_page_size(&self) -> usize {
+        self.backlog.page_size.max(1)
+    }
+
+    pub(crate) fn dialogue_max_lines(&self) -> usize {
+        self.dialogue.max_lines.max(1)
+    }
+
+    pub(crate) fn dialogue_overflow(&self) -> Overflow {
+        self.dialogue.overflow
+    }
+
+    pub(crate) fn auto_voice(&self) -> bool {
+        self.voice.auto
+    }
+
+    pub(crate) fn entry_script(&self) -> &str {
+        &self.title.entry_script
+    }
+
+    pub(crate) fn entry_label(&self) -> &str {
+        &self.title.entry_label
+    }
+
+    pub(crate) fn title_bgm(&self) -> &str {
+        &self.title.bgm
+    }
+
+    pub(crate) fn title_background(&self) -> &str {
+        &self.title.background
+    }
+
+    pub(crate) fn pre_title(&self) -> &[PreTitle] {
+        &self.title.pre_title
+    }
+
+    pub(crate) fn log_level(&self) -> &str {
+        &self.log.level
+    }
+
+    pub(crate) fn log_max_size(&self) -> u64 {
+        self.log.max_size_kb.max(1) * 1024
+    }
+
+    pub(crate) fn log_files(&self) -> usize {
+        self.log.files
+    }
+
     pub(crate) fn character_name_list(&self) -> HashSet<&String> {
         self.character.name_list()
     }
@@ -77,9 +177,70 @@ impl EngineConfig {
     pub(crate) fn character_list(&self) -> &HashMap<String, String> {
         self.character.list()
     }
+
+    pub(crate) fn character_of(&self, speaker: &str) -> Option<&String> {
+        self.character.find(speaker)
+    }
+}
+
+/// 启动时按依赖顺序加载全部配置，返回第一个错误，避免首次访问时才 panic。
+pub(crate) fn load_all() -> Result<(), ConfigError> {
+    LOADED_ENGINE_CONFIG.as_ref().map_err(Clone::clone)?;
+    cg::check()?;
+    figure::check()?;
+    voice::check()?;
+    textbox::check()?;
+    Ok(())
 }
 
-fn load_engine_config() -> EngineConfig {
-    let content = fs::read_to_string("./source/ini.toml").unwrap();
-    toml::from_str(&content).unwrap()
+pub(crate) fn read_config(path: &str) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|e| ConfigError::Read {
+        path: path.to_string(),
+        source: Arc::new(e),
+    })
+}
+
+/// 解析 TOML，出错时根据错误位置找出行号和所在行的键。
+pub(crate) fn parse_config<T: DeserializeOwned>(
+    path: &str,
+    content: &str,
+) -> Result<T, ConfigError> {
+    toml::from_str(content).map_err(|e| {
+        let span = e.span();
+        let line = span.as_ref().map(|span| {
+            content[..span.start.min(content.len())]
+                .matches('\n')
+                .count()
+                + 1
+        });
+        let key = line
+            .and_then(|line| content.lines().nth(line - 1))
+            .and_then(|text| {
+                let text = text.trim();
+                match text.split_once('=') {
+                    Some((key, _)) => Some(key.trim()),
+                    None if text.starts_with('[') => Some(text.trim_matches(['[', ']'])),
+                    None => None,
+                }
+            })
+            .filter(|key| !key.is_empty())
+            .map(str::to_string);
+        ConfigError::Parse {
+            path: path.to_string(),
+            key,
+            line,
+            message: e.message().to_string(),
+        }
+    })
+}
+
+fn load_engine_config() -> Result<EngineConfig, ConfigError> {
+    let root = ARGS.root();
+    let path = root.join("source/ini.toml").to_string_lossy().into_owned();
+    let mut config: EngineConfig = parse_config(&path, &read_config(&path)?)?;
+    config.initialize.resolve(&root);
+    if let Err(e) = fs::create_dir_all(&config.initialize.save_path) {
+        log::warn!("failed to create save directory: {e}");
+    }
+    Ok(config)
 }
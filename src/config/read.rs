@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const BITS: usize = u32::BITS as usize;
+
+/// 每个剧本已读块的位图，和 CG 一样分段存储。
+/// TOML 整数是 i64，用 32 位分段避免最高位溢出。
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub(crate) struct ReadMap(HashMap<String, Vec<u32>>);
+
+impl ReadMap {
+    pub(crate) fn is_read(&self, script: &str, index: usize) -> bool {
+        self.0
+            .get(script)
+            .and_then(|bits| bits.get(index / BITS))
+            .is_some_and(|bits| bits & (1u32 << (index % BITS)) != 0)
+    }
+
+    /// 标记为已读，返回是否是第一次读到。
+    pub(crate) fn mark(&mut self, script: &str, index: usize) -> bool {
+        let bits = self.0.entry(script.to_string()).or_default();
+        if bits.len() <= index / BITS {
+            bits.resize(index / BITS + 1, 0);
+        }
+        let mask = 1u32 << (index % BITS);
+        let first = bits[index / BITS] & mask == 0;
+        bits[index / BITS] |= mask;
+        first
+    }
+}
@@ -1,10 +1,11 @@
 use crate::config::ENGINE_CONFIG;
 use crate::error::{EngineError, SaveError};
 use crate::executors::executor::Executor;
-use crate::ui::initialize::SaveItem;
+use crate::script::Anchor;
+use crate::ui::initialize::{BackLogItem, SaveItem};
 use serde::{Deserialize, Serialize};
-use slint::{Image, ModelRc, ToSharedString, VecModel};
-use std::{fs, path::Path, rc::Rc};
+use slint::{Image, Model, ModelRc, ToSharedString, VecModel};
+use std::{collections::BTreeMap, fs, path::Path, rc::Rc};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct SaveData {
@@ -12,6 +13,63 @@ pub(crate) struct SaveData {
     pub(crate) block_index: usize,
     pub(crate) explain: String,
     pub(crate) image_path: String,
+    // 旧存档没有锚点字段，缺省时按块号读档
+    #[serde(default)]
+    pub(crate) label: String,
+    #[serde(default)]
+    pub(crate) offset: usize,
+    #[serde(default)]
+    pub(crate) hash: String,
+    #[serde(default)]
+    pub(crate) locked: bool,
+    #[serde(default)]
+    pub(crate) backlog: Vec<BacklogData>,
+    #[serde(default)]
+    pub(crate) variables: BTreeMap<String, String>,
+}
+
+/// 存档中的一条履历。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct BacklogData {
+    speaker: String,
+    text: String,
+    script: String,
+    index: usize,
+    #[serde(default)]
+    chara: String,
+    #[serde(default)]
+    voice: String,
+    #[serde(default)]
+    page: i32,
+}
+
+impl From<&BackLogItem> for BacklogData {
+    fn from(item: &BackLogItem) -> Self {
+        BacklogData {
+            speaker: item.front.to_string(),
+            text: item.back.to_string(),
+            script: item.script.to_string(),
+            index: item.index as usize,
+            chara: item.chara.to_string(),
+            voice: item.voice.to_string(),
+            page: item.page,
+        }
+    }
+}
+
+impl From<BacklogData> for BackLogItem {
+    fn from(data: BacklogData) -> Self {
+        BackLogItem {
+            front: data.speaker.to_shared_string(),
+            back: data.text.to_shared_string(),
+            script: data.script.to_shared_string(),
+            index: data.index as i32,
+            chara: data.chara.to_shared_string(),
+            voice: data.voice.to_shared_string(),
+            page: data.page,
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -25,80 +83,321 @@ impl SaveData {
         block_index: usize,
         explain: String,
         image_path: String,
+        anchor: Anchor,
     ) -> SaveData {
         SaveData {
             script,
             block_index,
             explain,
             image_path,
+            label: anchor.label,
+            offset: anchor.offset,
+            hash: anchor.hash,
+            locked: false,
+            backlog: Vec::new(),
+            variables: BTreeMap::new(),
+        }
+    }
+
+    fn empty() -> SaveData {
+        SaveData::new(
+            "".to_string(),
+            0,
+            "空的".to_string(),
+            "".to_string(),
+            Anchor::default(),
+        )
+    }
+}
+
+impl From<&SaveItem> for Anchor {
+    fn from(item: &SaveItem) -> Self {
+        Anchor {
+            label: item.label.to_string(),
+            offset: item.offset as usize,
+            hash: item.hash.to_string(),
         }
     }
 }
 
+impl From<&SaveItem> for SaveData {
+    fn from(item: &SaveItem) -> Self {
+        SaveData {
+            script: item.name.to_string(),
+            block_index: item.index as usize,
+            explain: item.explain.to_string(),
+            image_path: item
+                .bg
+                .path()
+                .and_then(|path| path.to_str())
+                .unwrap_or_default()
+                .to_string(),
+            label: item.label.to_string(),
+            offset: item.offset as usize,
+            hash: item.hash.to_string(),
+            locked: item.locked,
+            backlog: item
+                .backlog
+                .iter()
+                .map(|item| BacklogData::from(&item))
+                .collect(),
+            variables: item
+                .variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl From<SaveData> for SaveItem {
+    fn from(data: SaveData) -> Self {
+        SaveItem {
+            bg: Image::load_from_path(Path::new(&data.image_path)).unwrap_or_default(),
+            explain: data.explain.to_shared_string(),
+            index: data.block_index as i32,
+            name: data.script.to_shared_string(),
+            label: data.label.to_shared_string(),
+            offset: data.offset as i32,
+            hash: data.hash.to_shared_string(),
+            locked: data.locked,
+            backlog: Rc::new(VecModel::from(
+                data.backlog
+                    .into_iter()
+                    .map(BackLogItem::from)
+                    .collect::<Vec<_>>(),
+            ))
+            .into(),
+            variables: Rc::new(VecModel::from(
+                data.variables
+                    .into_iter()
+                    .map(|(name, value)| (name.to_shared_string(), value.to_shared_string()))
+                    .collect::<Vec<_>>(),
+            ))
+            .into(),
+        }
+    }
+}
+
+fn page_path(page: usize) -> String {
+    format!("{}{}.toml", ENGINE_CONFIG.save_path(), page)
+}
+
+fn read_page(path: &str) -> Result<Option<SaveDataWrapper>, EngineError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(toml::from_str(&content).map_err(|e| {
+            SaveError::Deserialize {
+                path: path.to_string(),
+                source: e,
+            }
+        })?)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn write_page(path: &str, wrapper: &SaveDataWrapper) -> Result<(), EngineError> {
+    let content = toml::to_string_pretty(wrapper).map_err(SaveError::from)?;
+    fs::write(path, content).map_err(|e| SaveError::Write {
+        path: path.to_string(),
+        source: e,
+    })?;
+    Ok(())
+}
+
+/// 调试命令里的栏位号从 1 开始按页连续编号，换算成 (页, 栏位)。
+pub(crate) fn slot_position(slot: i32) -> Option<(i32, i32)> {
+    let slots = ENGINE_CONFIG.save_slots() as i32;
+    let pages = ENGINE_CONFIG.save_pages() as i32;
+    (1..=slots * pages)
+        .contains(&slot)
+        .then(|| ((slot - 1) / slots, (slot - 1) % slots))
+}
+
+/// 崩溃时紧急存档的恢复栏位，不占用普通存档页。
+fn recovery_path() -> String {
+    format!("{}recovery.toml", ENGINE_CONFIG.save_path())
+}
+
+pub(crate) fn write_recovery(data: SaveData) -> Result<(), EngineError> {
+    write_page(
+        &recovery_path(),
+        &SaveDataWrapper {
+            save_data: vec![data],
+        },
+    )
+}
+
 impl Executor {
     pub(crate) fn load_save_data(&mut self) -> Result<(), EngineError> {
-        let mut load_items: Vec<ModelRc<SaveItem>> = Vec::with_capacity(10);
-        for i in 0..10 {
-            let path = format!("{}{}.toml", ENGINE_CONFIG.save_path(), i);
-            if let Ok(content) = fs::read_to_string(&path) {
-                let wrapper: SaveDataWrapper =
-                    toml::from_str(&content).map_err(|e| SaveError::Deserialize {
-                        path: path.clone(),
-                        source: e,
-                    })?;
-                let mut load_page = Vec::with_capacity(16);
-                for SaveData {
-                    script,
-                    block_index,
-                    explain,
-                    image_path,
-                } in wrapper.save_data
-                {
-                    let image = Image::load_from_path(Path::new(&image_path)).unwrap_or_default();
-                    load_page.push(SaveItem {
-                        bg: image,
-                        explain: explain.to_shared_string(),
-                        index: block_index as i32,
-                        name: script.to_shared_string(),
-                    });
+        let (pages, slots) = (ENGINE_CONFIG.save_pages(), ENGINE_CONFIG.save_slots());
+        let mut load_items: Vec<ModelRc<SaveItem>> = Vec::with_capacity(pages);
+        for i in 0..pages {
+            let path = page_path(i);
+            let mut save_data = match read_page(&path)? {
+                Some(wrapper) => wrapper.save_data,
+                None => {
+                    let wrapper = SaveDataWrapper {
+                        save_data: vec![SaveData::empty(); slots],
+                    };
+                    write_page(&path, &wrapper)?;
+                    wrapper.save_data
                 }
-                load_items.push(Rc::new(VecModel::from(load_page)).into());
-            } else {
-                let wrapper = SaveDataWrapper {
-                    save_data: vec![
-                        SaveData::new(
-                            "".to_string(),
-                            0,
-                            "空的".to_string(),
-                            "".to_string()
-                        );
-                        16
-                    ],
-                };
-                let content = toml::to_string_pretty(&wrapper).map_err(SaveError::from)?;
-                load_items.push(
-                    Rc::new(VecModel::from(vec![
-                        SaveItem {
-                            bg: Image::default(),
-                            explain: "空的".to_shared_string(),
-                            index: 0,
-                            name: "".to_shared_string(),
-                        };
-                        16
-                    ]))
-                    .into(),
-                );
-                fs::write(&path, content).map_err(|e| SaveError::Write {
-                    path: path.clone(),
-                    source: e,
-                })?;
-            }
+            };
+            // 栏位数变少时多出的存档仍保留在文件里，只是不显示
+            save_data.resize_with(slots, SaveData::empty);
+            let load_page: Vec<SaveItem> = save_data.into_iter().map(SaveItem::from).collect();
+            load_items.push(Rc::new(VecModel::from(load_page)).into());
         }
 
         let weak = self.get_weak();
         if let Some(window) = weak.upgrade() {
             window.set_save_items(Rc::new(VecModel::from(load_items)).into());
+            window.set_confirm_overwrite(ENGINE_CONFIG.confirm_overwrite());
+            window.set_has_recovery(Path::new(&recovery_path()).is_file());
         }
         Ok(())
     }
+
+    /// 从恢复栏位继续，读取后删除恢复存档。
+    pub(crate) fn execute_recover(&mut self) -> Result<(), EngineError> {
+        let path = recovery_path();
+        let data = read_page(&path)?.and_then(|wrapper| wrapper.save_data.into_iter().next());
+        self.execute_discard_recovery()?;
+        if let Some(data) = data {
+            self.execute_load_save(SaveItem::from(data))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn execute_discard_recovery(&self) -> Result<(), EngineError> {
+        let path = recovery_path();
+        if Path::new(&path).is_file() {
+            fs::remove_file(&path).map_err(|e| SaveError::Write { path, source: e })?;
+        }
+        if let Some(window) = self.get_weak().upgrade() {
+            window.set_has_recovery(false);
+        }
+        Ok(())
+    }
+
+    /// 把存档写入指定栏位，已锁定的栏位不会被覆盖。
+    pub(crate) fn store_save(
+        &self,
+        index: i32,
+        page_num: i32,
+        item: SaveItem,
+    ) -> Result<(), EngineError> {
+        if self
+            .save_slot(index, page_num)
+            .is_some_and(|slot| slot.locked)
+        {
+            log::warn!("save slot {index} on page {page_num} is locked");
+            return Ok(());
+        }
+        self.set_save_slot(index, page_num, item)
+    }
+
+    /// 读取指定栏位的存档，空栏位什么也不做。
+    pub(crate) fn execute_load_slot(
+        &mut self,
+        index: i32,
+        page_num: i32,
+    ) -> Result<(), EngineError> {
+        match self.save_slot(index, page_num) {
+            Some(item) => self.execute_load_save(item),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn execute_delete_save(&self, index: i32, page_num: i32) -> Result<(), EngineError> {
+        self.store_save(index, page_num, SaveItem::from(SaveData::empty()))
+    }
+
+    pub(crate) fn execute_copy_save(
+        &self,
+        from_index: i32,
+        from_page: i32,
+        to_index: i32,
+        to_page: i32,
+    ) -> Result<(), EngineError> {
+        if let Some(mut item) = self.save_slot(from_index, from_page) {
+            if item.name.is_empty() {
+                return Ok(());
+            }
+            item.locked = false;
+            self.store_save(to_index, to_page, item)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn execute_move_save(
+        &self,
+        from_index: i32,
+        from_page: i32,
+        to_index: i32,
+        to_page: i32,
+    ) -> Result<(), EngineError> {
+        let Some(item) = self.save_slot(from_index, from_page) else {
+            return Ok(());
+        };
+        if item.name.is_empty() {
+            return Ok(());
+        }
+        let target_locked = self
+            .save_slot(to_index, to_page)
+            .is_some_and(|slot| slot.locked);
+        if item.locked || target_locked {
+            log::warn!(
+                "cannot move save {from_index}@{from_page} to {to_index}@{to_page}: slot is locked"
+            );
+            return Ok(());
+        }
+        if (from_index, from_page) == (to_index, to_page) {
+            return Ok(());
+        }
+        self.set_save_slot(to_index, to_page, item)?;
+        self.set_save_slot(from_index, from_page, SaveItem::from(SaveData::empty()))
+    }
+
+    pub(crate) fn execute_lock_save(&self, index: i32, page_num: i32) -> Result<(), EngineError> {
+        if let Some(mut item) = self.save_slot(index, page_num) {
+            if !item.name.is_empty() {
+                item.locked = !item.locked;
+                self.set_save_slot(index, page_num, item)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn save_slot(&self, index: i32, page_num: i32) -> Option<SaveItem> {
+        let window = self.get_weak().upgrade()?;
+        window
+            .get_save_items()
+            .row_data(page_num as usize)?
+            .row_data(index as usize)
+    }
+
+    fn set_save_slot(&self, index: i32, page_num: i32, item: SaveItem) -> Result<(), EngineError> {
+        let Some(window) = self.get_weak().upgrade() else {
+            return Ok(());
+        };
+        let save_items = window.get_save_items();
+        let Some(save_page) = save_items.row_data(page_num as usize) else {
+            return Ok(());
+        };
+        save_page.set_row_data(index as usize, item);
+
+        let path = page_path(page_num as usize);
+        let mut save_data: Vec<SaveData> =
+            save_page.iter().map(|item| SaveData::from(&item)).collect();
+        // 保留配置缩小栏位后文件里多出的存档
+        if let Some(wrapper) = read_page(&path)? {
+            save_data.extend(wrapper.save_data.into_iter().skip(save_page.row_count()));
+        }
+        write_page(&path, &SaveDataWrapper { save_data })?;
+
+        save_items.set_row_data(page_num as usize, save_page);
+        window.set_save_items(save_items);
+        Ok(())
+    }
 }
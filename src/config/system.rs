@@ -40,12 +40,53 @@ impl AutoConfig {
     }
 }
 
+/// 快进的停止条件，按住 Ctrl 的快进不受这些限制。
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct SkipConfig {
+    skip_all: bool,
+    stop_at_choice: bool,
+    stop_at_video: bool,
+    stop_at_chapter: bool,
+}
+
+impl Default for SkipConfig {
+    fn default() -> Self {
+        SkipConfig {
+            skip_all: false,
+            stop_at_choice: true,
+            stop_at_video: true,
+            stop_at_chapter: false,
+        }
+    }
+}
+
+impl SkipConfig {
+    pub(crate) fn from_weak(weak: Weak<MainWindow>) -> Self {
+        if let Some(window) = weak.upgrade() {
+            SkipConfig {
+                skip_all: window.get_skip_all(),
+                stop_at_choice: window.get_stop_at_choice(),
+                stop_at_video: window.get_stop_at_video(),
+                stop_at_chapter: window.get_stop_at_chapter(),
+            }
+        } else {
+            unreachable!()
+        }
+    }
+}
+
 impl Executor {
     pub(crate) fn load_auto(&mut self) {
         let weak = self.get_weak();
         if let Some(window) = weak.upgrade() {
             window.set_is_wait(USER_CONFIG.is_wait());
             window.set_delay(USER_CONFIG.delay());
+            let skip = USER_CONFIG.skip();
+            window.set_skip_all(skip.skip_all);
+            window.set_stop_at_choice(skip.stop_at_choice);
+            window.set_stop_at_video(skip.stop_at_video);
+            window.set_stop_at_chapter(skip.stop_at_chapter);
         }
     }
 }
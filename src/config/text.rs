@@ -9,6 +9,10 @@ fn default_font() -> String {
     SYSTEM_FONTS.default_font()
 }
 
+fn default_mark_read() -> bool {
+    true
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct TextConfig {
     speed: f32,
@@ -18,6 +22,8 @@ pub(crate) struct TextConfig {
     // 老 user.toml 缺该字段也能解析
     #[serde(default = "default_font")]
     font: String,
+    #[serde(default = "default_mark_read")]
+    mark_read: bool,
 }
 
 impl Default for TextConfig {
@@ -28,6 +34,7 @@ impl Default for TextConfig {
             is_bold: false,
             show_shadow: false,
             font: SYSTEM_FONTS.default_font(),
+            mark_read: true,
         }
     }
 }
@@ -52,6 +59,10 @@ impl TextConfig {
         &self.font
     }
 
+    pub(crate) fn mark_read(&self) -> bool {
+        self.mark_read
+    }
+
     pub(crate) fn set_font(&mut self, font: String) {
         self.font = font;
     }
@@ -64,6 +75,7 @@ impl TextConfig {
                 is_bold: window.get_is_bold(),
                 show_shadow: window.get_show_shadow(),
                 font: window.get_dialogue_font().into(),
+                mark_read: window.get_mark_read(),
             }
         } else {
             unreachable!()
@@ -80,6 +92,7 @@ impl Executor {
             window.set_is_bold(USER_CONFIG.is_bold());
             window.set_show_shadow(USER_CONFIG.is_shadow());
             window.set_dialogue_font(USER_CONFIG.font().into());
+            window.set_mark_read(USER_CONFIG.mark_read());
 
             let list: Vec<slint::SharedString> =
                 SYSTEM_FONTS.families().iter().map(|s| s.into()).collect();
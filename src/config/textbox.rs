@@ -0,0 +1,130 @@
+use crate::config::{args::ARGS, parse_config, read_config};
+use crate::error::ConfigError;
+use crate::media::placeholder::load_image;
+use crate::parser::markup::parse_color;
+use crate::ui::initialize::TextboxStyle;
+use serde::Deserialize;
+use slint::Color;
+use std::collections::HashMap;
+
+lazy_static::lazy_static! {
+    static ref LOADED_TEXTBOX_THEME: Result<TextboxTheme, ConfigError> = load_theme();
+    pub(crate) static ref TEXTBOX_THEME: &'static TextboxTheme = LOADED_TEXTBOX_THEME
+        .as_ref()
+        .expect("textbox theme is checked by load_all at startup");
+}
+
+/// `@textbox style=default` 回到不做任何覆盖的样式。
+pub(crate) const DEFAULT_STYLE: &str = "default";
+
+pub(crate) fn check() -> Result<(), ConfigError> {
+    LOADED_TEXTBOX_THEME
+        .as_ref()
+        .map(|_| ())
+        .map_err(Clone::clone)
+}
+
+/// 说话人名牌的位置。
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum NamePosition {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Hidden,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StyleRead {
+    frame: Option<String>,
+    opacity: Option<f32>,
+    name_position: NamePosition,
+    color: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ThemeRead {
+    #[serde(default)]
+    style: HashMap<String, StyleRead>,
+}
+
+#[derive(Debug)]
+struct Style {
+    frame: Option<String>,
+    opacity: Option<f32>,
+    name_position: NamePosition,
+    color: Option<Color>,
+}
+
+/// `source/textbox.toml` 里定义的对话框样式，文件不存在时只有默认样式。
+#[derive(Debug, Default)]
+pub(crate) struct TextboxTheme {
+    styles: HashMap<String, Style>,
+}
+
+impl TextboxTheme {
+    /// `default` 总是存在，表示不做任何覆盖。
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        name == DEFAULT_STYLE || self.styles.contains_key(name)
+    }
+
+    /// 界面用的样式，未定义的样式按默认样式处理。
+    pub(crate) fn style(&self, name: &str) -> TextboxStyle {
+        let Some(style) = self.styles.get(name) else {
+            return TextboxStyle::default();
+        };
+        TextboxStyle {
+            has_frame: style.frame.is_some(),
+            frame: style.frame.as_deref().map(load_image).unwrap_or_default(),
+            custom_opacity: style.opacity.is_some(),
+            opacity: style.opacity.unwrap_or_default(),
+            name_position: match style.name_position {
+                NamePosition::Left | NamePosition::Hidden => 0,
+                NamePosition::Center => 1,
+                NamePosition::Right => 2,
+            },
+            hide_name: matches!(style.name_position, NamePosition::Hidden),
+            custom_color: style.color.is_some(),
+            color: style.color.unwrap_or_default(),
+        }
+    }
+}
+
+fn load_theme() -> Result<TextboxTheme, ConfigError> {
+    let root = ARGS.root();
+    let path = root.join("source/textbox.toml");
+    if !path.exists() {
+        return Ok(TextboxTheme::default());
+    }
+    let path = path.to_string_lossy().into_owned();
+    let theme: ThemeRead = parse_config(&path, &read_config(&path)?)?;
+
+    let mut styles = HashMap::with_capacity(theme.style.len());
+    for (name, style) in theme.style {
+        let color = match style.color {
+            Some(color) => Some(parse_color(&color).ok_or_else(|| ConfigError::Parse {
+                path: path.clone(),
+                key: Some(format!("style.{name}.color")),
+                line: None,
+                message: format!("invalid color `{color}`, expected #rgb, #rrggbb or #rrggbbaa"),
+            })?),
+            None => None,
+        };
+        // 和 ini.toml 一样，相对路径以工程根目录为基准
+        let frame = style
+            .frame
+            .map(|frame| root.join(frame).to_string_lossy().into_owned());
+        styles.insert(
+            name,
+            Style {
+                frame,
+                opacity: style.opacity.map(|opacity| opacity.clamp(0.0, 1.0)),
+                name_position: style.name_position,
+                color,
+            },
+        );
+    }
+    Ok(TextboxTheme { styles })
+}
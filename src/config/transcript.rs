@@ -0,0 +1,199 @@
+use crate::config::ENGINE_CONFIG;
+use crate::error::{EngineError, SaveError};
+use crate::executors::executor::Executor;
+use crate::parser::script_parser::Parser;
+use crate::script::{Script, CHOICE_SPEAKER, CHOSEN_SPEAKER};
+use crate::ui::initialize::BackLogItem;
+use std::collections::HashMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Markdown,
+    Html,
+    Text,
+}
+
+impl Format {
+    fn from_index(index: i32) -> Format {
+        match index {
+            1 => Format::Html,
+            2 => Format::Text,
+            _ => Format::Markdown,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Text => "txt",
+        }
+    }
+}
+
+/// 导出文本中的一行。
+enum Entry {
+    Script(String),
+    Label(String),
+    Line { speaker: String, text: String },
+    Choice(String),
+    Chosen(String),
+}
+
+/// 把履历整理成带剧本、标签分隔的条目，标签通过重新解析剧本查找。
+fn entries(backlog: &[BackLogItem]) -> Vec<Entry> {
+    let mut scripts: HashMap<String, Option<Script>> = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    let mut entries = Vec::new();
+
+    for item in backlog {
+        let name = item.script.to_string();
+        let script = scripts
+            .entry(name.clone())
+            .or_insert_with(|| Parser::load(&name).ok());
+        let label = script
+            .as_ref()
+            .map(|script| script.anchor((item.index as usize).saturating_sub(1)).label)
+            .unwrap_or_default();
+
+        if current.as_ref().is_none_or(|(script, _)| *script != name) {
+            entries.push(Entry::Script(name.clone()));
+            current = Some((name.clone(), String::new()));
+        }
+        if let Some((_, current_label)) = current.as_mut() {
+            if !label.is_empty() && *current_label != label {
+                entries.push(Entry::Label(label.clone()));
+                *current_label = label;
+            }
+        }
+
+        let text = item.back.to_string();
+        entries.push(match item.front.as_str() {
+            CHOICE_SPEAKER => Entry::Choice(text),
+            CHOSEN_SPEAKER => Entry::Chosen(text),
+            speaker => Entry::Line {
+                speaker: speaker.to_string(),
+                text,
+            },
+        });
+    }
+    entries
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render(entries: &[Entry], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Markdown => {
+            out.push_str("# Transcript\n");
+            for entry in entries {
+                let line = match entry {
+                    Entry::Script(name) => format!("\n## {name}\n"),
+                    Entry::Label(label) => format!("\n### {label}\n"),
+                    Entry::Line { speaker, text } => {
+                        let text = text.replace('\n', "  \n");
+                        if speaker.is_empty() {
+                            format!("\n{text}\n")
+                        } else {
+                            format!("\n**{speaker}**  \n{text}\n")
+                        }
+                    }
+                    Entry::Choice(name) => format!("\n> {CHOICE_SPEAKER}：{name}\n"),
+                    Entry::Chosen(choice) => format!(">\n> {CHOSEN_SPEAKER}：**{choice}**\n"),
+                };
+                out.push_str(&line);
+            }
+        }
+        Format::Html => {
+            out.push_str(concat!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+                "<title>Transcript</title>\n<style>\n",
+                "body { max-width: 48em; margin: 2em auto; font-family: sans-serif; line-height: 1.6; }\n",
+                ".speaker { font-weight: bold; }\n",
+                ".choice { border-left: 4px solid #b9a3e3; padding-left: 1em; color: #555; }\n",
+                "</style>\n</head>\n<body>\n<h1>Transcript</h1>\n",
+            ));
+            for entry in entries {
+                let line = match entry {
+                    Entry::Script(name) => format!("<h2>{}</h2>\n", escape_html(name)),
+                    Entry::Label(label) => format!("<h3>{}</h3>\n", escape_html(label)),
+                    Entry::Line { speaker, text } => {
+                        let text = escape_html(text).replace('\n', "<br>\n");
+                        if speaker.is_empty() {
+                            format!("<p>{text}</p>\n")
+                        } else {
+                            format!(
+                                "<p><span class=\"speaker\">{}</span><br>\n{text}</p>\n",
+                                escape_html(speaker)
+                            )
+                        }
+                    }
+                    Entry::Choice(name) => format!(
+                        "<p class=\"choice\">{CHOICE_SPEAKER}：{}</p>\n",
+                        escape_html(name)
+                    ),
+                    Entry::Chosen(choice) => format!(
+                        "<p class=\"choice\">{CHOSEN_SPEAKER}：<strong>{}</strong></p>\n",
+                        escape_html(choice)
+                    ),
+                };
+                out.push_str(&line);
+            }
+            out.push_str("</body>\n</html>\n");
+        }
+        Format::Text => {
+            for entry in entries {
+                let line = match entry {
+                    Entry::Script(name) => format!("==== {name} ====\n\n"),
+                    Entry::Label(label) => format!("---- {label} ----\n\n"),
+                    Entry::Line { speaker, text } => {
+                        if speaker.is_empty() {
+                            format!("{text}\n\n")
+                        } else {
+                            format!("{speaker}\n{text}\n\n")
+                        }
+                    }
+                    Entry::Choice(name) => format!("[{CHOICE_SPEAKER}] {name}\n"),
+                    Entry::Chosen(choice) => format!("[{CHOSEN_SPEAKER}] {choice}\n\n"),
+                };
+                out.push_str(&line);
+            }
+        }
+    }
+    out
+}
+
+impl Executor {
+    /// 把完整履历导出到存档目录，format 为 0 Markdown、1 HTML、2 纯文本。
+    pub(crate) fn execute_export_transcript(&self, format: i32) -> Result<(), EngineError> {
+        let format = Format::from_index(format);
+        let content = render(&entries(&self.full_backlog()), format);
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let path = format!(
+            "{}transcript-{time}.{}",
+            ENGINE_CONFIG.save_path(),
+            format.extension()
+        );
+        fs::write(&path, content).map_err(|e| SaveError::Write {
+            path: path.clone(),
+            source: e,
+        })?;
+
+        if let Some(window) = self.get_weak().upgrade() {
+            window.set_backlog_notice(format!("已导出到 {path}").into());
+        }
+        Ok(())
+    }
+}
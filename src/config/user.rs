@@ -1,6 +1,10 @@
 use crate::config::{
-    character_volume::CharacterVolumeConfig, font::SYSTEM_FONTS, system::AutoConfig,
-    text::TextConfig, volume::VolumeConfig, ENGINE_CONFIG,
+    character_volume::CharacterVolumeConfig,
+    font::SYSTEM_FONTS,
+    system::{AutoConfig, SkipConfig},
+    text::TextConfig,
+    volume::VolumeConfig,
+    ENGINE_CONFIG,
 };
 use crate::error::{EngineError, SaveError};
 use crate::ui::initialize::MainWindow;
@@ -15,6 +19,9 @@ lazy_static::lazy_static! {
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct UserConfig {
     auto: AutoConfig,
+    // 老 user.toml 没有这一节
+    #[serde(default)]
+    skip: SkipConfig,
     text: TextConfig,
     volume: VolumeConfig,
     character_volume: CharacterVolumeConfig,
@@ -24,6 +31,7 @@ impl UserConfig {
     fn default() -> Self {
         UserConfig {
             auto: AutoConfig::default(),
+            skip: SkipConfig::default(),
             text: TextConfig::default(),
             volume: VolumeConfig::default(),
             character_volume: CharacterVolumeConfig::default_from_engine(),
@@ -38,6 +46,10 @@ impl UserConfig {
         self.auto.is_wait()
     }
 
+    pub(crate) fn skip(&self) -> &SkipConfig {
+        &self.skip
+    }
+
     pub(crate) fn main_volume(&self) -> f32 {
         self.volume.main()
     }
@@ -70,6 +82,10 @@ impl UserConfig {
         self.text.font()
     }
 
+    pub(crate) fn mark_read(&self) -> bool {
+        self.text.mark_read()
+    }
+
     pub(crate) fn character_volume(&self, name: &str) -> f32 {
         *self.character_volume.volumes.get(name).unwrap()
     }
@@ -77,6 +93,7 @@ impl UserConfig {
     pub(crate) fn from_weak(weak: Weak<MainWindow>) -> Self {
         UserConfig {
             auto: AutoConfig::from_weak(weak.clone()),
+            skip: SkipConfig::from_weak(weak.clone()),
             text: TextConfig::from_weak(weak.clone()),
             volume: VolumeConfig::from_weak(weak.clone()),
             character_volume: CharacterVolumeConfig::from_weak(weak),
@@ -1,10 +1,21 @@
-use crate::config::ENGINE_CONFIG;
+use crate::config::{parse_config, read_config, ENGINE_CONFIG};
+use crate::error::ConfigError;
 use serde::{Deserialize, Deserializer, Serialize};
-use std::{collections::HashMap, fs};
+use std::collections::HashMap;
 use tokio::time::Duration;
 
 lazy_static::lazy_static! {
-    pub(crate) static ref VOICE_LENGTH: VoiceLength = load_voice();
+    static ref LOADED_VOICE_LENGTH: Result<VoiceLength, ConfigError> = load_voice();
+    pub(crate) static ref VOICE_LENGTH: &'static VoiceLength = LOADED_VOICE_LENGTH
+        .as_ref()
+        .expect("voice config is checked by load_all at startup");
+}
+
+pub(crate) fn check() -> Result<(), ConfigError> {
+    LOADED_VOICE_LENGTH
+        .as_ref()
+        .map(|_| ())
+        .map_err(Clone::clone)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -30,16 +41,11 @@ impl VoiceLength {
     }
 }
 
-fn load_voice() -> VoiceLength {
+fn load_voice() -> Result<VoiceLength, ConfigError> {
     let mut voice_length = HashMap::new();
     for char in &ENGINE_CONFIG.character_name_list() {
-        let content = fs::read_to_string(format!(
-            "{}{}/length.toml",
-            ENGINE_CONFIG.voice_path(),
-            char
-        ))
-        .unwrap();
-        let item: LengthWrapper = toml::from_str(&content).unwrap();
+        let path = format!("{}{}/length.toml", ENGINE_CONFIG.voice_path(), char);
+        let item: LengthWrapper = parse_config(&path, &read_config(&path)?)?;
         voice_length.insert(
             char.to_string(),
             item.cast
@@ -49,7 +55,7 @@ fn load_voice() -> VoiceLength {
         );
     }
 
-    VoiceLength { voice_length }
+    Ok(VoiceLength { voice_length })
 }
 
 pub(crate) fn deserialize_duration_from_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
@@ -0,0 +1,273 @@
+use crate::config::{save_load::slot_position, ENGINE_CONFIG};
+use crate::executors::{events::Event, executor::Executor};
+use crate::parser::script_parser::Parser;
+use crate::ui::initialize::MainWindow;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use slint::{ComponentHandle, Model};
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// 客户端长时间不读取时放弃写入，避免卡住 UI 线程。
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+/// get_state 附带的履历条数。
+const BACKLOG_TAIL: usize = 10;
+
+thread_local! {
+    static EXECUTOR: RefCell<Option<Executor>> = const { RefCell::new(None) };
+}
+
+// 事件和回复写同一个连接，共用一把锁防止两行交错
+static CLIENTS: Mutex<Vec<(u64, TcpStream)>> = Mutex::new(Vec::new());
+static NEXT_CLIENT: AtomicU64 = AtomicU64::new(0);
+
+/// 控制服务的请求，每行一个 JSON 对象，`id` 原样带回。
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    #[serde(flatten)]
+    command: ControlCommand,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum ControlCommand {
+    Advance,
+    Choose {
+        index: usize,
+    },
+    Jump {
+        script: String,
+        label: Option<String>,
+    },
+    Save {
+        slot: i32,
+    },
+    Load {
+        slot: i32,
+    },
+    GetState,
+    Screenshot {
+        path: Option<String>,
+    },
+}
+
+/// 在 127.0.0.1 上启动控制服务，必须在 UI 线程调用。
+pub(crate) fn start(executor: &Executor, port: u16) {
+    EXECUTOR.with(|slot| *slot.borrow_mut() = Some(executor.clone()));
+
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("failed to start control server on port {port}: {e}");
+            return;
+        }
+    };
+    log::info!("control server listening on 127.0.0.1:{port}");
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    thread::spawn(move || serve(stream));
+                }
+                Err(e) => log::warn!("control connection failed: {e}"),
+            }
+        }
+    });
+}
+
+/// 广播事件，写失败的客户端直接断开。
+pub(crate) fn broadcast(event: &Event) {
+    let Ok(line) = serde_json::to_string(event) else {
+        return;
+    };
+    let mut clients = CLIENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    clients.retain_mut(|(_, stream)| writeln!(stream, "{line}").is_ok());
+}
+
+fn serve(stream: TcpStream) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    let writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            log::warn!("control connection {peer} failed: {e}");
+            return;
+        }
+    };
+    let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+    let id = NEXT_CLIENT.fetch_add(1, Ordering::Relaxed);
+    CLIENTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((id, writer));
+    log::info!("control client {peer} connected");
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => dispatch(request),
+            Err(e) => {
+                json!({ "id": Value::Null, "ok": false, "error": format!("invalid request: {e}") })
+            }
+        };
+        let mut clients = CLIENTS.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some((_, writer)) = clients.iter_mut().find(|(client, _)| *client == id) else {
+            break;
+        };
+        if writeln!(writer, "{response}").is_err() {
+            break;
+        }
+    }
+
+    CLIENTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|(client, _)| *client != id);
+    log::info!("control client {peer} disconnected");
+}
+
+/// 把命令交给 UI 线程执行，等它返回结果。
+fn dispatch(request: Request) -> Value {
+    let Request { id, command } = request;
+    let (tx, rx) = mpsc::channel();
+    let posted = slint::invoke_from_event_loop(move || {
+        let result = match EXECUTOR.with(|slot| slot.borrow().clone()) {
+            Some(mut executor) => executor.execute_control(command),
+            None => Err("engine is not ready".to_string()),
+        };
+        let _ = tx.send(result);
+    });
+    let result = match posted {
+        Ok(()) => rx
+            .recv()
+            .unwrap_or_else(|_| Err("event loop stopped".to_string())),
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(result) => json!({ "id": id, "ok": true, "result": result }),
+        Err(error) => json!({ "id": id, "ok": false, "error": error }),
+    }
+}
+
+impl Executor {
+    fn execute_control(&mut self, command: ControlCommand) -> Result<Value, String> {
+        let window = self
+            .get_weak()
+            .upgrade()
+            .ok_or_else(|| "window is closed".to_string())?;
+        match command {
+            ControlCommand::Advance => {
+                if window.get_current_screen() != 2 {
+                    return Err("not on the story screen".to_string());
+                }
+                self.execute_script().map_err(|e| e.to_string())?;
+                Ok(Value::Null)
+            }
+            ControlCommand::Choose { index } => {
+                let (_, choice) = window
+                    .get_choose_branch()
+                    .row_data(index)
+                    .ok_or_else(|| format!("no choice #{index}"))?;
+                self.execute_choose(choice.clone())
+                    .map_err(|e| e.to_string())?;
+                Ok(json!(choice.as_str()))
+            }
+            ControlCommand::Jump { script, label } => {
+                if let Some(label) = &label {
+                    let parsed = Parser::load(&script).map_err(|e| e.to_string())?;
+                    if parsed.find_label(label).is_none() {
+                        return Err(format!("label `{label}` not found in `{script}`"));
+                    }
+                }
+                self.execute_debug_jump(script, label)
+                    .map_err(|e| e.to_string())?;
+                Ok(Value::Null)
+            }
+            ControlCommand::Save { slot } => {
+                let (page, index) = position(slot)?;
+                self.execute_save(index, page).map_err(|e| e.to_string())?;
+                Ok(Value::Null)
+            }
+            ControlCommand::Load { slot } => {
+                let (page, index) = position(slot)?;
+                self.execute_load_slot(index, page)
+                    .map_err(|e| e.to_string())?;
+                Ok(Value::Null)
+            }
+            ControlCommand::GetState => {
+                let state = self
+                    .engine_state(BACKLOG_TAIL)
+                    .ok_or_else(|| "script is busy".to_string())?;
+                let mut state = serde_json::to_value(state).map_err(|e| e.to_string())?;
+                state["screen"] = json!(window.get_current_screen());
+                state["speaker"] = json!(window.get_speaker().as_str());
+                state["dialogue"] = window
+                    .get_dialogue_lines()
+                    .iter()
+                    .map(|line| {
+                        json!(line
+                            .iter()
+                            .map(|span| span.text.to_string())
+                            .collect::<String>())
+                    })
+                    .collect();
+                state["choices"] = window
+                    .get_choose_branch()
+                    .iter()
+                    .map(|(_, choice)| json!(choice.as_str()))
+                    .collect();
+                Ok(state)
+            }
+            ControlCommand::Screenshot { path } => screenshot(&window, path).map(Value::from),
+        }
+    }
+}
+
+fn position(slot: i32) -> Result<(i32, i32), String> {
+    slot_position(slot).ok_or_else(|| {
+        format!(
+            "save slot must be between 1 and {}",
+            ENGINE_CONFIG.save_slots() * ENGINE_CONFIG.save_pages()
+        )
+    })
+}
+
+/// 截取当前窗口存为 PNG，没给路径时存到存档目录下。
+fn screenshot(window: &MainWindow, path: Option<String>) -> Result<String, String> {
+    let path = path.unwrap_or_else(|| {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+        format!("{}screenshot-{time}.png", ENGINE_CONFIG.save_path())
+    });
+    let buffer = window.window().take_snapshot().map_err(|e| e.to_string())?;
+    let file = File::create(&path).map_err(|e| format!("failed to create `{path}`: {e}"))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), buffer.width(), buffer.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(buffer.as_bytes()))
+        .map_err(|e| format!("failed to write `{path}`: {e}"))?;
+    Ok(path)
+}
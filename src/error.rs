@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,6 +12,9 @@ pub(crate) enum EngineError {
     #[error("save error: {0}")]
     Save(#[from] SaveError),
 
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+
     #[error("media error: {0}")]
     Media(#[from] MediaError),
 
@@ -76,6 +80,16 @@ pub(crate) enum ExecutorError {
     #[error("CG metadata not found for id {0}")]
     CgMetadataMissing(u64),
 
+    #[error("invalid record file `{path}` at line {line}: {content}")]
+    InvalidRecord {
+        path: String,
+        line: usize,
+        content: String,
+    },
+
+    #[error("replay diverged from the recording: {0} mismatched event(s)")]
+    ReplayMismatch(usize),
+
     #[allow(dead_code)]
     #[error("invalid executors state: {0}")]
     InvalidState(&'static str),
@@ -83,7 +97,6 @@ pub(crate) enum ExecutorError {
 
 #[derive(Debug, Error)]
 pub(crate) enum SaveError {
-    #[allow(dead_code)]
     #[error("failed to read save file `{path}`: {source}")]
     Read {
         path: String,
@@ -109,6 +122,29 @@ pub(crate) enum SaveError {
     },
 }
 
+/// 配置文件错误，尽量指出文件、键和行号。
+/// 需要 Clone 是因为加载结果缓存在 lazy_static 里，所以 io::Error 放在 Arc 里。
+/// io::Error 只通过 source() 给出，不写进消息。
+#[derive(Debug, Clone, Error)]
+pub(crate) enum ConfigError {
+    #[error("failed to read config file `{path}`")]
+    Read {
+        path: String,
+        #[source]
+        source: Arc<std::io::Error>,
+    },
+
+    #[error("invalid config `{path}`{}{}: {message}",
+        line.map(|line| format!(" at line {line}")).unwrap_or_default(),
+        key.as_ref().map(|key| format!(", key `{key}`")).unwrap_or_default())]
+    Parse {
+        path: String,
+        key: Option<String>,
+        line: Option<usize>,
+        message: String,
+    },
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum MediaError {
     #[error("failed to open media file `{path}`: {source}")]
@@ -47,7 +47,7 @@ impl AutoExecutor {
                     is_auto_clone.store(false, Ordering::Relaxed);
                     start = true;
                     if let Err(e) = reset_tx.send(()).await {
-                        eprintln!("auto reset channel closed: {e}");
+                        log::error!("auto reset channel closed: {e}");
                         return;
                     }
                 }
@@ -72,7 +72,7 @@ impl AutoExecutor {
                         }
                     } => {
                         if let Err(e) = auto_tx.send(()) {
-                            eprintln!("auto trigger channel closed: {e}");
+                            log::error!("auto trigger channel closed: {e}");
                             return;
                         }
                         current_delay = None;
@@ -99,9 +99,9 @@ impl AutoExecutor {
             Duration::from_millis(100),
             move || {
                 if is_auto.load(Ordering::Relaxed) && rx.try_recv().is_ok() {
-                    executor
-                        .execute_script()
-                        .expect("auto execute_script failed");
+                    if let Err(e) = executor.execute_script() {
+                        executor.report_error("自动播放", e);
+                    }
                 }
             },
         );
@@ -0,0 +1,155 @@
+use crate::config::{save_load::slot_position, ENGINE_CONFIG};
+use crate::error::EngineError;
+use crate::executors::executor::Executor;
+use crate::parser::script_parser::Parser;
+use std::fmt::Write;
+
+/// 调试面板显示的履历条数。
+const BACKLOG_TAIL: usize = 3;
+
+/// 调试控制台支持的命令。
+enum ConsoleCommand {
+    Jump {
+        script: String,
+        label: Option<String>,
+    },
+    Set {
+        name: String,
+        value: String,
+    },
+    UnlockAllCg,
+    Reload,
+    Save(i32),
+}
+
+const USAGE: &str =
+    "commands: jump script[:label] | set var value | unlock cg all | reload | save N";
+
+impl ConsoleCommand {
+    fn parse(line: &str) -> Result<ConsoleCommand, String> {
+        let mut words = line.split_whitespace();
+        let command = match (words.next(), words.next()) {
+            (Some("jump"), Some(target)) => {
+                let (script, label) = match target.split_once(':') {
+                    Some((script, label)) => (script, Some(label.to_string())),
+                    None => (target, None),
+                };
+                ConsoleCommand::Jump {
+                    script: script.to_string(),
+                    label,
+                }
+            }
+            (Some("set"), Some(name)) => {
+                let value = words.collect::<Vec<_>>().join(" ");
+                if value.is_empty() {
+                    return Err(format!("usage: set {name} value"));
+                }
+                return Ok(ConsoleCommand::Set {
+                    name: name.to_string(),
+                    value,
+                });
+            }
+            (Some("unlock"), Some("cg")) if words.next() == Some("all") => {
+                ConsoleCommand::UnlockAllCg
+            }
+            (Some("reload"), None) => ConsoleCommand::Reload,
+            (Some("save"), Some(slot)) => ConsoleCommand::Save(
+                slot.parse()
+                    .map_err(|_| format!("invalid save slot `{slot}`"))?,
+            ),
+            _ => return Err(USAGE.to_string()),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected argument `{extra}`")),
+            None => Ok(command),
+        }
+    }
+}
+
+impl Executor {
+    /// 执行一行调试命令，返回显示在控制台里的结果。
+    pub(crate) fn execute_console(&mut self, line: &str) -> String {
+        let command = match ConsoleCommand::parse(line.trim()) {
+            Ok(command) => command,
+            Err(message) => return message,
+        };
+        log::debug!("console: {line}");
+        match self.apply_console(command) {
+            Ok(message) => message,
+            Err(e) => {
+                self.report_error("调试命令", e);
+                format!("`{line}` failed, see log for details")
+            }
+        }
+    }
+
+    fn apply_console(&mut self, command: ConsoleCommand) -> Result<String, EngineError> {
+        match command {
+            ConsoleCommand::Jump { script, label } => {
+                // 先确认标签存在，避免跳到半路
+                if let Some(label) = &label {
+                    if Parser::load(&script)?.find_label(label).is_none() {
+                        return Ok(format!("label `{label}` not found in `{script}`"));
+                    }
+                }
+                let target = match &label {
+                    Some(label) => format!("{script}:{label}"),
+                    None => script.clone(),
+                };
+                self.execute_debug_jump(script, label)?;
+                Ok(format!("jumped to {target}"))
+            }
+            ConsoleCommand::Set { name, value } => {
+                let message = format!("{name} = {value}");
+                self.set_variable(name, value);
+                Ok(message)
+            }
+            ConsoleCommand::UnlockAllCg => {
+                self.unlock_all_cg()?;
+                Ok("all CG unlocked".to_string())
+            }
+            ConsoleCommand::Reload => {
+                self.execute_reload_script()?;
+                Ok(format!("reloaded `{}`", self.script_name()))
+            }
+            ConsoleCommand::Save(slot) => {
+                let Some((page, index)) = slot_position(slot) else {
+                    return Ok(format!(
+                        "save slot must be between 1 and {}",
+                        ENGINE_CONFIG.save_slots() * ENGINE_CONFIG.save_pages()
+                    ));
+                };
+                self.execute_save(index, page)?;
+                Ok(format!("saved to page {} slot {}", page + 1, index + 1))
+            }
+        }
+    }
+
+    /// 调试面板显示的状态文本。
+    pub(crate) fn debug_info(&self) -> String {
+        let Some(state) = self.engine_state(BACKLOG_TAIL) else {
+            return "script is busy".to_string();
+        };
+        let mut out = String::new();
+        let _ = writeln!(out, "script: {}  block: {}", state.script, state.index);
+        let _ = writeln!(out, "label: {}", state.label);
+        let _ = writeln!(out, "bgm: {}", state.bgm);
+        let _ = writeln!(out, "figures:");
+        for figure in &state.figures {
+            let _ = writeln!(out, "  {figure}");
+        }
+        let _ = writeln!(out, "pending:");
+        for command in &state.pending {
+            let _ = writeln!(out, "  {command}");
+        }
+        let _ = writeln!(out, "variables:");
+        for (name, value) in &state.variables {
+            let _ = writeln!(out, "  {name} = {value}");
+        }
+        let _ = writeln!(out, "backlog:");
+        for (speaker, text) in &state.backlog {
+            let _ = writeln!(out, "  {speaker}: {text}");
+        }
+        out
+    }
+}
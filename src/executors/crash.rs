@@ -0,0 +1,165 @@
+use crate::config::{save_load::write_recovery, ENGINE_CONFIG};
+use crate::executors::executor::Executor;
+use crate::logger;
+use crate::parser::script_parser::Command;
+use serde::Serialize;
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write,
+    fs,
+    panic::{self, PanicHookInfo},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 崩溃报告里保留的最近命令条数。
+const RECENT_COMMANDS: usize = 32;
+/// 崩溃报告里附带的履历条数。
+const BACKLOG_TAIL: usize = 10;
+
+thread_local! {
+    static RECENT: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+    static EXECUTOR: RefCell<Option<Executor>> = const { RefCell::new(None) };
+}
+
+/// 引擎状态快照，用于崩溃报告、调试面板和控制服务。
+#[derive(Default, Serialize)]
+pub(crate) struct EngineState {
+    pub(crate) script: String,
+    pub(crate) index: usize,
+    pub(crate) label: String,
+    pub(crate) bgm: String,
+    pub(crate) figures: Vec<String>,
+    pub(crate) pending: Vec<String>,
+    pub(crate) variables: Vec<(String, String)>,
+    pub(crate) backlog: Vec<(String, String)>,
+}
+
+/// 记录执行过的命令，只保留最近的若干条。
+pub(crate) fn record_command(command: &Command) {
+    RECENT.with(|recent| {
+        if let Ok(mut recent) = recent.try_borrow_mut() {
+            if recent.len() == RECENT_COMMANDS {
+                recent.pop_front();
+            }
+            recent.push_back(format!("{command:?}"));
+        }
+    });
+}
+
+/// 安装 panic hook：写崩溃报告，并把当前位置紧急存到恢复栏位。
+pub(crate) fn install(executor: &Executor) {
+    EXECUTOR.with(|slot| *slot.borrow_mut() = Some(executor.clone()));
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let path = format!("{}crash-{time}.txt", ENGINE_CONFIG.save_path());
+        match fs::write(&path, report(info)) {
+            Ok(()) => log::error!("game crashed, report written to {path}"),
+            Err(e) => log::error!("game crashed, failed to write report `{path}`: {e}"),
+        }
+        log::logger().flush();
+        default_hook(info);
+    }));
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string());
+    match info.location() {
+        Some(location) => format!("{payload} at {location}"),
+        None => payload,
+    }
+}
+
+fn report(info: &PanicHookInfo) -> String {
+    // hook 里不能再 panic，借用失败的状态一律跳过
+    let (state, recovery) = EXECUTOR.with(|slot| {
+        let Ok(slot) = slot.try_borrow() else {
+            return (None, None);
+        };
+        let Some(executor) = slot.as_ref() else {
+            return (None, None);
+        };
+        let recovery = executor
+            .recovery_data()
+            .map(|data| write_recovery(data).map_err(|e| e.to_string()));
+        executor.save_read();
+        (executor.engine_state(BACKLOG_TAIL), recovery)
+    });
+
+    let mut out = String::new();
+    let _ = writeln!(out, "RustEng crash report");
+    let _ = writeln!(out, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "time: {}", logger::timestamp());
+    let _ = writeln!(
+        out,
+        "thread: {}",
+        thread::current().name().unwrap_or("unnamed")
+    );
+    let _ = writeln!(out, "panic: {}", panic_message(info));
+
+    let _ = writeln!(out, "\n[state]");
+    match &state {
+        Some(state) => {
+            let _ = writeln!(out, "script: {}", state.script);
+            let _ = writeln!(out, "block: {}", state.index);
+            let _ = writeln!(out, "label: {}", state.label);
+            let _ = writeln!(out, "bgm: {}", state.bgm);
+            let _ = writeln!(out, "figures:");
+            for figure in &state.figures {
+                let _ = writeln!(out, "  {figure}");
+            }
+            let _ = writeln!(out, "pending delay commands:");
+            for command in &state.pending {
+                let _ = writeln!(out, "  {command}");
+            }
+            let _ = writeln!(out, "variables:");
+            for (name, value) in &state.variables {
+                let _ = writeln!(out, "  {name} = {value}");
+            }
+        }
+        None => {
+            let _ = writeln!(out, "unavailable");
+        }
+    }
+    let _ = writeln!(
+        out,
+        "recovery: {}",
+        match &recovery {
+            Some(Ok(())) => "saved to recovery.toml".to_string(),
+            Some(Err(e)) => format!("failed: {e}"),
+            None => "unavailable".to_string(),
+        }
+    );
+
+    let _ = writeln!(out, "\n[recent commands]");
+    RECENT.with(|recent| {
+        if let Ok(recent) = recent.try_borrow() {
+            for command in recent.iter() {
+                let _ = writeln!(out, "{command}");
+            }
+        }
+    });
+
+    let _ = writeln!(out, "\n[backlog]");
+    if let Some(state) = &state {
+        for (speaker, text) in &state.backlog {
+            let _ = writeln!(out, "{speaker}: {text}");
+        }
+    }
+
+    let _ = writeln!(out, "\n[backtrace]");
+    let _ = writeln!(out, "{}", Backtrace::force_capture());
+    out
+}
@@ -1,9 +1,10 @@
 use crate::error::EngineError;
 use crate::executors::executor::Executor;
+use crate::logger;
 use crate::parser::script_parser::Command;
 use std::{
     collections::VecDeque,
-    sync::{Arc, RwLock},
+    sync::{Arc, PoisonError, RwLock},
 };
 use tokio::{
     sync::mpsc::Sender,
@@ -15,6 +16,8 @@ pub(crate) struct DelayTX {
     delay_tx: Sender<Command>,
     skip_tx: Sender<()>,
     clear_tx: Sender<()>,
+    // 还在等待延时的命令，供调试面板查看
+    pending: Arc<RwLock<VecDeque<Command>>>,
 }
 
 #[derive(Clone)]
@@ -55,6 +58,21 @@ impl DelayChannels {
             .expect("clear_loop_move_tx send fali");
     }
 
+    /// 崩溃报告也会调用，锁中毒时照样读出。
+    pub(crate) fn pending(&self) -> Vec<Command> {
+        [&self.delay_tx, &self.delay_move_tx, &self.loop_move_tx]
+            .iter()
+            .flat_map(|tx| {
+                tx.pending
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     pub(crate) fn skip_all(&self) {
         self.delay_tx
             .skip_tx
@@ -79,12 +97,12 @@ fn try_send_loop(tx: &Sender<Command>, cmd: Command) {
             let tx_clone = tx.clone();
             tokio::spawn(async move {
                 if let Err(e) = tx_clone.send(cmd).await {
-                    eprintln!("delay tx send failed: {e:?}");
+                    log::error!("delay tx send failed: {e:?}");
                 }
             });
         }
         Err(e) => {
-            eprintln!("try_send other error: {e:?}");
+            log::error!("try_send other error: {e:?}");
         }
     }
 }
@@ -104,6 +122,8 @@ impl DelayExecutor {
         let timer = slint::Timer::default();
         let command = Arc::new(RwLock::new(VecDeque::new()));
         let command_clone = command.clone();
+        let pending = Arc::new(RwLock::new(VecDeque::new()));
+        let current_figure = pending.clone();
 
         let executor = Self {
             timer,
@@ -111,32 +131,42 @@ impl DelayExecutor {
             command,
         };
         tokio::spawn(async move {
-            let mut current_figure: VecDeque<Command> = VecDeque::new();
-
             loop {
+                // 锁不能跨 await 持有，先取出队首的延时
+                let delay = match current_figure.read().unwrap().front() {
+                    Some(Command::Figure { delay, .. }) | Some(Command::Move { delay, .. }) => {
+                        Some(
+                            delay
+                                .clone()
+                                .unwrap_or_default()
+                                .parse::<u64>()
+                                .unwrap_or(0),
+                        )
+                    }
+                    _ => None,
+                };
+
                 tokio::select! {
                     Some(figure) = rx.recv()=> {
-                        current_figure.push_back(figure);
+                        current_figure.write().unwrap().push_back(figure);
                     }
 
                     // 延迟完成
                     _ = async {
-                        if let Some(Command::Figure {delay, ..})
-                        | Some(Command::Move {delay, ..}) = current_figure.front(){
-                            sleep(Duration::from_millis(
-                                delay.clone().unwrap_or_default().parse::<u64>().unwrap_or(0),
-                            )).await;
+                        if let Some(delay) = delay {
+                            sleep(Duration::from_millis(delay)).await;
                         } else {
                             std::future::pending::<()>().await
                         }
                     } => {
-                        if let Some(cmd) = current_figure.pop_front() {
+                        if let Some(cmd) = current_figure.write().unwrap().pop_front() {
                             command_clone.write().unwrap().push_back(cmd);
                         }
                     }
 
                     // 重置请求
                     _ = skip_rx.recv() => {
+                        let mut current_figure = current_figure.write().unwrap();
                         while let Some(figure) = current_figure.pop_front() {
                             if let Command::Figure {..} = figure {
                                 command_clone.write().unwrap().push_back(figure);
@@ -150,7 +180,7 @@ impl DelayExecutor {
 
                     // 清空请求
                     _ = clear_rx.recv() => {
-                        current_figure.clear();
+                        current_figure.write().unwrap().clear();
                     }
                 }
             }
@@ -162,6 +192,7 @@ impl DelayExecutor {
                 delay_tx: tx,
                 skip_tx,
                 clear_tx,
+                pending,
             },
         )
     }
@@ -184,7 +215,7 @@ impl DelayExecutor {
                         Ok(())
                     };
                     if let Err(e) = result {
-                        eprintln!("delay executors failed: {e}");
+                        logger::log_error("delay executors", &e);
                     }
                 }
             },
@@ -0,0 +1,31 @@
+#[cfg(feature = "control")]
+use crate::control;
+use crate::executors::record;
+use serde::Serialize;
+use std::fmt;
+
+/// 引擎对外的事件：录制回放用来比对，控制服务推送给客户端。
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    Line { speaker: String, text: String },
+    Choices { choices: Vec<String> },
+    Background { name: String },
+}
+
+impl fmt::Display for Event {
+    // 录制文件里的一行，字符串按 Debug 转义，不会跨行
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Line { speaker, text } => write!(f, "line {speaker:?} {text:?}"),
+            Event::Choices { choices } => write!(f, "choices {choices:?}"),
+            Event::Background { name } => write!(f, "background {name:?}"),
+        }
+    }
+}
+
+pub(crate) fn emit(event: Event) {
+    record::record_event(&event);
+    #[cfg(feature = "control")]
+    control::broadcast(&event);
+}
@@ -1,24 +1,48 @@
+use crate::config::args::ARGS;
 use crate::config::cg::get_cg;
+use crate::config::initialize::PreTitle;
+use crate::config::read::ReadMap;
+use crate::config::save_load::SaveData;
 use crate::config::{
-    cg::CG_CONFIG, extra::save_extra_config, figure::FIGURE_CONFIG, save_load::SaveData,
-    user::save_user_config, voice::VOICE_LENGTH, ENGINE_CONFIG,
+    cg::CG_CONFIG,
+    extra::save_extra_config,
+    figure::{figure_config, FigureConfig},
+    textbox::{DEFAULT_STYLE, TEXTBOX_THEME},
+    user::save_user_config,
+    voice::VOICE_LENGTH,
+    ENGINE_CONFIG,
 };
-use crate::error::{EngineError, SaveError};
+use crate::error::EngineError;
 use crate::executors::{
+    crash::{self, EngineState},
     delay_executor::{DelayChannels, DelayTX},
-    text_executor::{DisplayText, TextTX},
+    events::{self, Event},
+    lip_sync_executor::LipSync,
+    nvl::{self, NvlPage},
+    rollback::{History, Snapshot},
+    speaker,
+    text_executor::{self, show_dialogue, DisplayText, Frame, TextTX},
+    text_layout,
+    title::Splash,
 };
+use crate::logger;
 use crate::media::{
-    player::{MediaPlayer, PreBgm, PreBgm::Play},
+    placeholder::load_image,
+    player::{audio_duration, MediaPlayer, PreBgm, PreBgm::Play},
     video_player::{VideoContext, VideoPlayer},
 };
-use crate::parser::script_parser::{Command, Commands, Parser};
-use crate::script::{Label, Script};
-use crate::ui::initialize::{CharacterVolume, FigureItem, MainWindow, SaveItem};
-use slint::{Image, Model, SharedString, ToSharedString, VecModel, Weak};
+use crate::parser::{
+    markup,
+    script_parser::{Command, Commands, NvlAction, Parser, TextboxAction},
+};
+use crate::script::{
+    Anchor, Label, PreItems, Script, TextboxState, CHOICE_SPEAKER, CHOSEN_SPEAKER,
+};
+use crate::ui::initialize::{BackLogItem, CharacterVolume, FigureItem, MainWindow, SaveItem};
+use slint::{Image, Model, ModelRc, SharedString, ToSharedString, VecModel, Weak};
 use std::{
     cell::RefCell,
-    fs,
+    collections::{BTreeMap, HashMap},
     path::Path,
     rc::Rc,
     sync::{Arc, RwLock},
@@ -29,6 +53,7 @@ use tokio::sync::mpsc::Sender;
 pub(crate) enum Jump {
     Label(Label),
     Index((String, i32)),
+    Anchor((String, i32, Anchor)),
 }
 
 fn parse_position(position: &str, distance: &str) -> (f32, f32, f32) {
@@ -68,17 +93,75 @@ fn parse_position(position: &str, distance: &str) -> (f32, f32, f32) {
     (base_x, base_y, width_ratio)
 }
 
+/// 自动配音：说话人对应角色的 `voice/角色名/台词ID.ogg` 存在时返回 (角色名, 台词 ID)。
+fn auto_voice(speaker: &str, line_id: &str) -> Option<(String, String)> {
+    if !ENGINE_CONFIG.auto_voice() {
+        return None;
+    }
+    let name = figure_config().character_of(speaker)?.to_string();
+    let path = format!("{}/{}/{}.ogg", ENGINE_CONFIG.voice_path(), name, line_id);
+    Path::new(&path)
+        .is_file()
+        .then(|| (name, line_id.to_string()))
+}
+
+/// 表情对应的口型图层，没有配置时为空。
+fn load_mouths(config: &FigureConfig, name: &str, distance: &str, face: &str) -> ModelRc<Image> {
+    let mouths: Vec<Image> = config
+        .mouths(name, face)
+        .map(|layers| {
+            layers
+                .iter()
+                .map(|layer| {
+                    load_image(&format!(
+                        "{}{}/{}/{}.png",
+                        ENGINE_CONFIG.figure_path(),
+                        name,
+                        distance,
+                        layer
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Rc::new(VecModel::from(mouths)).into()
+}
+
+/// 履历筛选用的角色列表，按角色名排序保证下拉框顺序稳定。
+fn backlog_speakers() -> Vec<(String, String)> {
+    let mut speakers: Vec<(String, String)> = ENGINE_CONFIG
+        .character_list()
+        .iter()
+        .map(|(name, full_name)| (name.clone(), full_name.clone()))
+        .collect();
+    speakers.sort();
+    speakers
+}
+
 #[derive(Clone)]
 pub(crate) struct Executor {
     script: Rc<RefCell<Script>>,
     media_player: Rc<RefCell<MediaPlayer>>,
     cg: Rc<RefCell<Vec<u64>>>,
+    read: Rc<RefCell<ReadMap>>,
     weak: Weak<MainWindow>,
     text: Arc<RwLock<DisplayText>>,
     choose_lock: Rc<RefCell<bool>>,
     video_context: Rc<RefCell<VideoContext>>,
     figure_items: Rc<VecModel<FigureItem>>,
     figure_id: Rc<RefCell<i32>>,
+    history: Rc<RefCell<History>>,
+    splash: Rc<RefCell<Splash>>,
+    variables: Rc<RefCell<BTreeMap<String, String>>>,
+    // 每个角色最近一次显示的表情，侧边头像用
+    faces: Rc<RefCell<HashMap<String, Image>>>,
+    // 正在按配音音量对口型的角色
+    lip_sync: Rc<RefCell<Option<LipSync>>>,
+    // length.toml 里没有的自动配音时长，按 (角色, 台词编号) 缓存，ogg 要整段解码才能知道
+    voice_durations: Rc<RefCell<HashMap<(String, String), Duration>>>,
+    // NVL 模式下的当前页，ADV 模式下为 None
+    nvl: Rc<RefCell<Option<NvlPage>>>,
+    textbox: Rc<RefCell<TextboxState>>,
     text_tx: Option<TextTX>,
     auto_tx: Option<Sender<Duration>>,
     delay_channels: Option<DelayChannels>,
@@ -86,23 +169,43 @@ pub(crate) struct Executor {
 
 impl Executor {
     pub(crate) fn new(weak: Weak<MainWindow>) -> Result<Executor, EngineError> {
-        let script = Parser::load("ky01")?;
+        let script = Parser::load(ENGINE_CONFIG.entry_script())?;
 
         let figure_items = Rc::new(VecModel::<FigureItem>::default());
         if let Some(window) = weak.upgrade() {
             window.set_figure_items(figure_items.clone().into());
+            window.set_can_rollback(ENGINE_CONFIG.rollback_limit() > 0);
+            window.set_dev_mode(ARGS.dev());
+            window.set_backlog_page_size(ENGINE_CONFIG.backlog_page_size() as i32);
+            let speakers: Vec<SharedString> = std::iter::once("全部角色".to_shared_string())
+                .chain(
+                    backlog_speakers()
+                        .into_iter()
+                        .map(|(_, full_name)| full_name.to_shared_string()),
+                )
+                .collect();
+            window.set_backlog_speakers(Rc::new(VecModel::from(speakers)).into());
         }
 
         Ok(Executor {
             script: Rc::new(RefCell::new(script)),
             media_player: Rc::new(RefCell::new(MediaPlayer::new()?)),
             cg: Rc::new(RefCell::new(Vec::new())),
+            read: Rc::new(RefCell::new(ReadMap::default())),
             weak,
             text: Arc::new(RwLock::new(DisplayText::new())),
             choose_lock: Rc::new(RefCell::new(false)),
             video_context: Rc::new(RefCell::new(VideoContext::default())),
             figure_items,
             figure_id: Rc::new(RefCell::new(0)),
+            history: Rc::new(RefCell::new(History::new(ENGINE_CONFIG.rollback_limit()))),
+            splash: Rc::new(RefCell::new(Splash::default())),
+            variables: Rc::new(RefCell::new(BTreeMap::new())),
+            faces: Rc::new(RefCell::new(HashMap::new())),
+            lip_sync: Rc::new(RefCell::new(None)),
+            voice_durations: Rc::new(RefCell::new(HashMap::new())),
+            nvl: Rc::new(RefCell::new(None)),
+            textbox: Rc::new(RefCell::new(TextboxState::default())),
             text_tx: None,
             auto_tx: None,
             delay_channels: None,
@@ -113,10 +216,43 @@ impl Executor {
         self.weak.clone()
     }
 
+    /// 回调出错时不再 panic，输出错误并在画面上提示。
+    pub(crate) fn report_error(&self, context: &str, error: EngineError) {
+        logger::log_error(context, &error);
+        if let Some(window) = self.weak.upgrade() {
+            window.set_error_toast(format!("{context}失败：{error}").into());
+        }
+    }
+
     pub(crate) fn set_cg(&mut self, cg: Vec<u64>) {
         *self.cg.borrow_mut() = cg;
     }
 
+    pub(crate) fn set_read(&mut self, read: ReadMap) {
+        *self.read.borrow_mut() = read;
+    }
+
+    /// 下一个要执行的剧情块是否读过，快进据此决定是否停下。
+    pub(crate) fn next_block_read(&self) -> bool {
+        let script = self.script.borrow();
+        self.read.borrow().is_read(script.name(), script.index())
+    }
+
+    pub(crate) fn is_choosing(&self) -> bool {
+        *self.choose_lock.borrow()
+    }
+
+    /// 只在剧情画面、没有选项和视频时热重载剧本。
+    pub(crate) fn can_reload_script(&self) -> bool {
+        self.weak.upgrade().is_some_and(|window| {
+            window.get_current_screen() == 2 && !window.get_is_video() && !self.is_choosing()
+        })
+    }
+
+    pub(crate) fn script_name(&self) -> String {
+        self.script.borrow().name().to_string()
+    }
+
     pub(crate) fn set_text_tx(&mut self, text_tx: Sender<Arc<RwLock<DisplayText>>>) {
         self.text_tx = Some(text_tx);
     }
@@ -140,27 +276,67 @@ impl Executor {
 
     pub(crate) fn unlock(&mut self, index: usize) {
         let mut cg = self.cg.borrow_mut();
-        cg[index / 64] |= 1u64 << index;
+        cg[index / 64] |= 1u64 << (index % 64);
     }
 
     pub(crate) fn execute_backlog(&self) -> Result<(), EngineError> {
         if let Some(window) = self.weak.upgrade() {
             let script = self.script.borrow();
-            let backlog = script.backlog();
+            let backlog = script.backlog(ENGINE_CONFIG.backlog_page_size());
             window.set_backlogs(Rc::new(VecModel::from(backlog)).into());
         }
 
         Ok(())
     }
 
+    /// 打开履历界面时清空上次的检索条件。
+    pub(crate) fn execute_backlog_open(&self) -> Result<(), EngineError> {
+        if let Some(window) = self.weak.upgrade() {
+            window.set_backlog_notice(SharedString::default());
+        }
+        self.script.borrow_mut().set_backlog_filter("", None);
+        self.execute_backlog()
+    }
+
     pub(crate) fn execute_backlog_change(&mut self, offset: i32) -> Result<(), EngineError> {
         {
             let mut script = self.script.borrow_mut();
-            script.set_offset(offset);
+            script.set_offset(offset, ENGINE_CONFIG.backlog_page_size());
         }
         self.execute_backlog()
     }
 
+    /// 按文本检索、按说话人筛选，speaker 为 0 表示全部角色。
+    pub(crate) fn execute_backlog_search(
+        &mut self,
+        query: String,
+        speaker: i32,
+    ) -> Result<(), EngineError> {
+        let speaker = (speaker > 0)
+            .then(|| backlog_speakers().into_iter().nth(speaker as usize - 1))
+            .flatten();
+        self.script
+            .borrow_mut()
+            .set_backlog_filter(query.trim(), speaker);
+        self.execute_backlog()
+    }
+
+    pub(crate) fn full_backlog(&self) -> Vec<BackLogItem> {
+        self.script.borrow().full_backlog().to_vec()
+    }
+
+    pub(crate) fn execute_backlog_top(&mut self) -> Result<(), EngineError> {
+        self.script
+            .borrow_mut()
+            .backlog_top(ENGINE_CONFIG.backlog_page_size());
+        self.execute_backlog()
+    }
+
+    pub(crate) fn execute_backlog_bottom(&mut self) -> Result<(), EngineError> {
+        self.script.borrow_mut().backlog_bottom();
+        self.execute_backlog()
+    }
+
     pub(crate) fn execute_backlog_jump(
         &mut self,
         name: String,
@@ -180,56 +356,346 @@ impl Executor {
         Ok(())
     }
 
+    /// 当前位置对应的存档，剧本正被借用时返回 None。
+    fn current_save_item(&self) -> Option<SaveItem> {
+        let window = self.weak.upgrade()?;
+        let script = self.script.try_borrow().ok()?;
+        let anchor = script.anchor(script.index().saturating_sub(1));
+        Some(SaveItem {
+            bg: window.get_bg().0,
+            explain: SharedString::from(script.explain()),
+            index: script.index() as i32,
+            name: SharedString::from(script.name()),
+            label: anchor.label.to_shared_string(),
+            offset: anchor.offset as i32,
+            hash: anchor.hash.to_shared_string(),
+            locked: false,
+            backlog: Rc::new(VecModel::from(
+                script.saved_backlog(ENGINE_CONFIG.backlog_limit()),
+            ))
+            .into(),
+            variables: Rc::new(VecModel::from(
+                self.variables
+                    .borrow()
+                    .iter()
+                    .map(|(name, value)| (name.to_shared_string(), value.to_shared_string()))
+                    .collect::<Vec<_>>(),
+            ))
+            .into(),
+        })
+    }
+
     pub(crate) fn execute_save(&mut self, index: i32, page_num: i32) -> Result<(), EngineError> {
-        if let Some(window) = self.weak.upgrade() {
-            let script = self.script.borrow();
-            let bg = window.get_bg();
-            let exists_save_items = window.get_save_items();
-            let save_page = exists_save_items.row_data(page_num as usize).unwrap();
-            save_page.set_row_data(
-                index as usize,
-                SaveItem {
-                    bg: bg.0.clone(),
-                    explain: SharedString::from(script.explain()),
-                    index: script.index() as i32,
-                    name: SharedString::from(script.name()),
-                },
-            );
-            exists_save_items.set_row_data(page_num as usize, save_page);
-            fs::write(
-                format!("{}{}.toml", ENGINE_CONFIG.save_path(), index),
-                toml::to_string_pretty(&SaveData::new(
-                    script.name().to_string(),
-                    script.index(),
-                    script.explain().to_string(),
-                    bg.0.path().unwrap().to_str().unwrap().to_string(),
-                ))
-                .map_err(SaveError::from)?,
-            )
-            .map_err(|e| SaveError::Write {
-                path: format!("{}{}.toml", ENGINE_CONFIG.save_path(), index),
-                source: e,
-            })?;
-            window.set_save_items(exists_save_items);
+        if let Some(item) = self.current_save_item() {
+            self.store_save(index, page_num, item)?;
         }
+        self.save_read();
 
         Ok(())
     }
 
+    /// 崩溃时紧急存档用的数据，还在标题画面时没有可存的位置。
+    pub(crate) fn recovery_data(&self) -> Option<SaveData> {
+        let window = self.weak.upgrade()?;
+        if window.get_current_screen() == 0 || window.get_current_screen() == 6 {
+            return None;
+        }
+        self.current_save_item()
+            .filter(|item| !item.name.is_empty())
+            .map(|item| SaveData::from(&item))
+    }
+
+    /// 崩溃报告和调试面板用的引擎状态，只做不会 panic 的借用。
+    pub(crate) fn engine_state(&self, backlog_tail: usize) -> Option<EngineState> {
+        let script = self.script.try_borrow().ok()?;
+        let backlog = script.full_backlog();
+        Some(EngineState {
+            script: script.name().to_string(),
+            index: script.index(),
+            label: script.anchor(script.index().saturating_sub(1)).label,
+            bgm: script.current_bgm().to_string(),
+            figures: self
+                .figure_items
+                .iter()
+                .map(|figure| {
+                    format!(
+                        "{} {} body={} face={} at ({}, {}) offset ({}, {})",
+                        figure.name,
+                        figure.distance,
+                        figure
+                            .body
+                            .path()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                        figure
+                            .face
+                            .path()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                        figure.base_x,
+                        figure.base_y,
+                        figure.x_offset,
+                        figure.y_offset
+                    )
+                })
+                .collect(),
+            pending: self
+                .delay_channels
+                .as_ref()
+                .map(|channels| {
+                    channels
+                        .pending()
+                        .iter()
+                        .map(|command| format!("{command:?}"))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            variables: self
+                .variables
+                .try_borrow()
+                .map(|variables| {
+                    variables
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            backlog: backlog[backlog.len().saturating_sub(backlog_tail)..]
+                .iter()
+                .map(|item| (item.front.to_string(), item.back.to_string()))
+                .collect(),
+        })
+    }
+
+    /// 剧本 `@set` 和调试控制台设置的变量，随存档和回滚保存。
+    pub(crate) fn set_variable(&self, name: String, value: String) {
+        self.variables.borrow_mut().insert(name, value);
+    }
+
+    /// 把内存里的已读记录连同 CG 写入 extra.toml，失败只记日志。
+    /// 崩溃时也会调用，正被借用时跳过。
+    pub(crate) fn save_read(&self) {
+        if self.cg.try_borrow().is_err() || self.read.try_borrow().is_err() {
+            return;
+        }
+        if let Err(e) = save_extra_config(self.cg.clone(), self.read.clone()) {
+            log::warn!("failed to save read text: {e}");
+        }
+    }
+
+    /// 调试用：解锁全部 CG 并写入 extra.toml。
+    pub(crate) fn unlock_all_cg(&mut self) -> Result<(), EngineError> {
+        for index in 1..=CG_CONFIG.length() {
+            self.unlock(index);
+        }
+        save_extra_config(self.cg.clone(), self.read.clone())
+    }
+
+    /// 调试用：跳到指定剧本的标签，没有标签时从剧本开头开始，保留履历。
+    pub(crate) fn execute_debug_jump(
+        &mut self,
+        name: String,
+        label: Option<String>,
+    ) -> Result<(), EngineError> {
+        let jump = match label {
+            Some(label) => Jump::Label((name, label)),
+            None => Jump::Index((name, 0)),
+        };
+        self.load_jump(jump, None)
+    }
+
     pub(crate) fn execute_load(&mut self, name: String, index: i32) -> Result<(), EngineError> {
         if !name.is_empty() {
-            let weak = self.weak.clone();
-            if let Some(window) = weak.upgrade() {
-                window.set_current_screen(2);
-                window.set_current_choose(0);
+            self.load_jump(Jump::Index((name, index - 1)), None)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn execute_load_save(&mut self, item: SaveItem) -> Result<(), EngineError> {
+        if !item.name.is_empty() {
+            let anchor = Anchor::from(&item);
+            let backlog = item.backlog.iter().collect();
+            *self.variables.borrow_mut() = item
+                .variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            self.load_jump(
+                Jump::Anchor((item.name.to_string(), item.index - 1, anchor)),
+                Some(backlog),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 从 ini.toml 配置的入口开始新游戏。
+    pub(crate) fn execute_new_game(&mut self) -> Result<(), EngineError> {
+        self.media_player.borrow().stop_bgm();
+        self.variables.borrow_mut().clear();
+        self.faces.borrow_mut().clear();
+        let name = ENGINE_CONFIG.entry_script().to_string();
+        *self.script.borrow_mut() = Parser::load(&name)?;
+        let label = ENGINE_CONFIG.entry_label();
+        let jump = if label.is_empty() {
+            Jump::Index((name, 0))
+        } else {
+            Jump::Label((name, label.to_string()))
+        };
+        self.load_jump(jump, Some(Vec::new()))
+    }
+
+    /// 开发模式下重新解析当前剧本，按标签锚点回到原来的位置。
+    pub(crate) fn execute_reload_script(&mut self) -> Result<(), EngineError> {
+        let (name, index, anchor, backlog) = {
+            let script = self.script.borrow();
+            let index = script.index().saturating_sub(1);
+            (
+                script.name().to_string(),
+                index,
+                script.anchor(index),
+                script.saved_backlog(usize::MAX),
+            )
+        };
+        *self.script.borrow_mut() = Parser::load(&name)?;
+        self.load_jump(Jump::Anchor((name, index as i32, anchor)), Some(backlog))
+    }
+
+    /// 开发模式下按新的立绘配置刷新画面上的立绘，图片也重新读取。
+    pub(crate) fn execute_reload_figures(&self) {
+        let figure_config = figure_config();
+        let model = self.figure_items.clone();
+        for i in 0..model.row_count() {
+            let Some(mut item) = model.row_data(i) else {
+                continue;
+            };
+            let stem = |image: &Image| {
+                image
+                    .path()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            };
+            let (body, face) = (stem(&item.body), stem(&item.face));
+            let name = item.name.to_string();
+            let (Some(body_para), Some(face_para), Some(offset)) = figure_config.find(&name) else {
+                log::warn!("figure `{name}` is no longer configured");
+                continue;
+            };
+            if let Some(rate) = body_para.get(&body) {
+                item.rate = *rate;
+            }
+            if let Some((face_x, face_y)) = face_para.get(&face) {
+                item.face_x = *face_x;
+                item.face_y = *face_y;
             }
-            self.execute_jump(Jump::Index((name, index - 1)))?;
-            self.execute_script()?;
+            item.offset = *offset;
+            let dir = format!("{}{}/{}", ENGINE_CONFIG.figure_path(), name, item.distance);
+            item.body = load_image(&format!("{dir}/{body}.png"));
+            item.face = load_image(&format!("{dir}/{face}.png"));
+            item.mouths = load_mouths(&figure_config, &name, &item.distance, &face);
+            item.mouth_frame = 0;
+            model.set_row_data(i, item);
         }
+    }
+
+    /// 启动时先播放标题前的内容，没有配置时直接进入标题画面。
+    pub(crate) fn execute_start_title(&mut self) -> Result<(), EngineError> {
+        self.splash.borrow_mut().start(ENGINE_CONFIG.pre_title());
+        self.execute_splash_next()
+    }
 
+    pub(crate) fn execute_splash_next(&mut self) -> Result<(), EngineError> {
+        let next = self.splash.borrow_mut().next();
+        let Some(window) = self.weak.upgrade() else {
+            return Ok(());
+        };
+        match next {
+            None => {
+                self.save_read();
+                window.set_current_screen(0);
+                let background = ENGINE_CONFIG.title_background();
+                if !background.is_empty() {
+                    let path = format!("{}{}.png", ENGINE_CONFIG.background_path(), background);
+                    window.set_title_bg(load_image(&path));
+                }
+                let bgm = ENGINE_CONFIG.title_bgm();
+                if !bgm.is_empty() {
+                    self.play_bgm(bgm.to_string())?;
+                }
+            }
+            Some(PreTitle::Image { image, duration }) => {
+                window.set_current_screen(6);
+                let path = format!("{}{}.png", ENGINE_CONFIG.background_path(), image);
+                window.set_splash_image(load_image(&path));
+
+                let timer = slint::Timer::default();
+                let executor = self.clone();
+                timer.start(
+                    slint::TimerMode::SingleShot,
+                    Duration::from_secs_f32(duration.max(0.0)),
+                    move || {
+                        let mut executor = executor.clone();
+                        slint::spawn_local(async move {
+                            if let Err(e) = executor.execute_splash_next() {
+                                logger::log_error("pre-title sequence", &e);
+                            }
+                        })
+                        .expect("pre-title timer: no slint event loop");
+                    },
+                );
+                self.splash.borrow_mut().set_timer(timer);
+            }
+            Some(PreTitle::Video { video }) => {
+                window.set_current_screen(6);
+                window.set_splash_image(Image::default());
+                self.start_video(&video)?;
+            }
+        }
         Ok(())
     }
 
+    /// 点击跳过当前项，视频在停止时会自动进入下一项。
+    pub(crate) async fn execute_splash_skip(&mut self) -> Result<(), EngineError> {
+        if !self.splash.borrow().is_active() {
+            return Ok(());
+        }
+        if self.video_context.borrow().get_video_player_ref().is_some() {
+            self.execute_stop_video().await
+        } else {
+            self.execute_splash_next()
+        }
+    }
+
+    /// 跳到读档位置；带有存档履历时替换当前履历。
+    fn load_jump(
+        &mut self,
+        jump: Jump,
+        backlog: Option<Vec<BackLogItem>>,
+    ) -> Result<(), EngineError> {
+        let weak = self.weak.clone();
+        if let Some(window) = weak.upgrade() {
+            // 从标题画面读档时先停掉标题 BGM
+            if window.get_current_screen() == 0 {
+                self.media_player.borrow().stop_bgm();
+            }
+            window.set_current_screen(2);
+            window.set_current_choose(0);
+        }
+        *self.choose_lock.borrow_mut() = false;
+        self.history.borrow_mut().clear();
+        if self.nvl.borrow().is_some() {
+            self.reset_nvl(false);
+        }
+        self.set_textbox(TextboxState::default());
+        self.execute_jump(jump)?;
+        if let Some(backlog) = backlog {
+            self.script.borrow_mut().set_backlog(backlog);
+        }
+        self.execute_script()
+    }
+
     pub(crate) fn execute_get_ex(&self) -> Result<(), EngineError> {
         let ex_items = get_cg(self.cg.clone())?;
 
@@ -270,22 +736,31 @@ impl Executor {
     }
 
     pub(crate) fn execute_choose(&mut self, choice: SharedString) -> Result<(), EngineError> {
+        {
+            let mut history = self.history.borrow_mut();
+            if !ENGINE_CONFIG.allow_rechoose()
+                && history.chosen().is_some_and(|chosen| *chosen != choice)
+            {
+                return Ok(());
+            }
+            history.record_choice(choice.clone());
+        }
         *self.choose_lock.borrow_mut() = false;
 
         let label: (String, String);
         {
             let scr = self.script.clone();
-            let scr = scr.borrow();
+            let mut scr = scr.borrow_mut();
             label = scr.get_choice_label(&choice).unwrap().clone();
+            scr.push_backlog(CHOSEN_SPEAKER.to_shared_string(), choice.clone(), None);
         }
 
         if let Some(window) = self.weak.upgrade() {
             window.set_choose_branch(Rc::new(VecModel::from(vec![])).into());
             window.set_current_choose(0);
-            window.set_speaker("".into());
-            window.set_dialogue_1(choice);
-            window.set_dialogue_2(SharedString::default());
-            window.set_dialogue_3(SharedString::default());
+            self.set_speaker(&window, "");
+            self.push_nvl_line(&window, "", &choice);
+            show_dialogue(&window, Frame::whole(choice.to_string()));
         }
 
         if let Some(window) = self.weak.upgrade() {
@@ -303,22 +778,29 @@ impl Executor {
         {
             let mut script = self.script.borrow_mut();
             let backlog = script.to_owned().take_backlog();
-            let jump_index = match label {
-                Jump::Label((name, label)) => {
-                    if name != script.name() {
-                        let mut scr = Parser::load(&name)?;
-                        scr.set_backlog(backlog);
-                        *script = scr;
-                    }
-                    script.find_label(&label).copied()
+            let name = match &label {
+                Jump::Label((name, _)) | Jump::Index((name, _)) | Jump::Anchor((name, _, _)) => {
+                    name
                 }
-                Jump::Index((name, index)) => {
-                    if name != script.name() {
-                        let mut scr = Parser::load(&name)?;
-                        scr.set_backlog(backlog);
-                        *script = scr;
+            };
+            if name != script.name() {
+                let mut scr = Parser::load(name)?;
+                scr.set_backlog(backlog);
+                *script = scr;
+            }
+            let jump_index = match label {
+                Jump::Label((_, label)) => script.find_label(&label).copied(),
+                Jump::Index((_, index)) => Some(index as usize),
+                Jump::Anchor((name, index, anchor)) => {
+                    let (resolved, exact) = script.resolve_anchor(&anchor, index as usize);
+                    if !exact {
+                        log::warn!(
+                            target: "save",
+                            "save anchor `{}`+{} does not match `{name}`, relocated to block {resolved}",
+                            anchor.label, anchor.offset
+                        );
                     }
-                    Some(index as usize)
+                    Some(resolved)
                 }
             };
 
@@ -363,6 +845,16 @@ impl Executor {
         Ok(())
     }
 
+    pub(crate) fn execute_fast_forward(
+        &mut self,
+        tx: Sender<bool>,
+        hold: bool,
+    ) -> Result<(), EngineError> {
+        tx.try_send(hold)?;
+
+        Ok(())
+    }
+
     pub(crate) fn execute_script(&mut self) -> Result<(), EngineError> {
         {
             let scr = self.script.clone();
@@ -395,6 +887,19 @@ impl Executor {
             return Ok(());
         }
 
+        // 分页显示的台词先翻到下一页
+        let next_page = self.text.write().unwrap().next_page();
+        if next_page {
+            self.text_tx.clone().unwrap().try_send(self.text.clone())?;
+            if let Some(window) = self.weak.upgrade() {
+                if window.get_is_auto() {
+                    let delay = Duration::from_millis((window.get_delay() * 1000.0) as u64);
+                    self.auto_tx.clone().unwrap().try_send(delay)?;
+                }
+            }
+            return Ok(());
+        }
+
         let mut duration = Duration::default();
         let mut is_wait = true;
         let mut is_auto = false;
@@ -412,10 +917,17 @@ impl Executor {
             return Ok(());
         }
 
+        self.refresh_snapshot();
+
         let mut commands = Commands::EmptyCmd;
+        // 已读记录只改内存，存档、回到标题、退出和崩溃时才写入 extra.toml
         {
             let scr = self.script.clone();
             let mut scr = scr.borrow_mut();
+            let first_read = self.read.borrow_mut().mark(scr.name(), scr.index());
+            if let Some(window) = self.weak.upgrade() {
+                window.set_is_read(!first_read);
+            }
             if let Some(cmds) = scr.next_command() {
                 commands = cmds.clone();
             }
@@ -432,6 +944,8 @@ impl Executor {
             }
         };
 
+        self.push_snapshot();
+
         if is_wait {
             duration += delay;
         }
@@ -445,12 +959,27 @@ impl Executor {
 
     pub(crate) fn apply_command(&mut self, command: Command) -> Result<Duration, EngineError> {
         let mut duration = Duration::from_secs(0);
+        crash::record_command(&command);
 
         if let Some(window) = self.weak.upgrade() {
             let mut scr = self.script.borrow_mut();
-            let (pre_bg, pre_bgm, pre_figures) = scr.pre_items();
+            let PreItems {
+                pre_bg,
+                pre_bgm,
+                pre_figures,
+                pre_nvl,
+                pre_textbox,
+            } = scr.pre_items();
             drop(scr);
 
+            if let Some(state) = pre_textbox {
+                self.set_textbox(state);
+            }
+            if let Some(on) = pre_nvl {
+                if on || self.nvl.borrow().is_some() {
+                    self.reset_nvl(on);
+                }
+            }
             if let Some(bg) = pre_bg {
                 self.show_bg(&bg)?;
             }
@@ -491,28 +1020,57 @@ impl Executor {
                         choose_branch.push((index as i32, SharedString::from(choice.0.clone())));
                     }
                     script.push_backlog(
-                        "选择支".to_shared_string(),
+                        CHOICE_SPEAKER.to_shared_string(),
                         explain.to_shared_string(),
                         None,
                     );
+                    events::emit(Event::Choices {
+                        choices: choose_branch
+                            .iter()
+                            .map(|(_, choice)| choice.to_string())
+                            .collect(),
+                    });
                     window.set_choose_branch(Rc::new(VecModel::from(choose_branch)).into());
                     window.set_current_choose(choices.len() as i32);
                 }
-                Command::Dialogue { speaker, text } => {
+                Command::Dialogue {
+                    speaker,
+                    text,
+                    line_id,
+                } => {
+                    // 履历、存档预览和事件里只保留纯文本
+                    let plain = markup::strip(&text);
+                    // 履历里记录玩家看到的名字，排版前先确定有没有侧边头像
+                    let name = self.set_speaker(&window, &speaker);
+                    let page = self.push_nvl_line(&window, &name, &text);
                     {
                         let mut script = self.script.borrow_mut();
-                        let voice = script.pre_voice();
-                        script.set_explain(&text);
-                        script.push_backlog(
-                            speaker.to_shared_string(),
-                            text.replace("{nns}", "").to_shared_string(),
-                            voice,
-                        );
+                        let voice = match script.pre_voice() {
+                            Some(voice) => Some(voice),
+                            None => match auto_voice(&speaker, &line_id) {
+                                Some((name, voice)) => {
+                                    duration += self.play_auto_voice(&name, &voice)?;
+                                    Some((name.to_shared_string(), voice.to_shared_string()))
+                                }
+                                None => None,
+                            },
+                        };
+                        script.set_explain(&plain.replace('\n', ""));
+                        match page {
+                            Some(page) => {
+                                script.push_nvl_backlog(name, plain.to_shared_string(), voice, page)
+                            }
+                            None => script.push_backlog(name, plain.to_shared_string(), voice),
+                        }
                     }
-                    window.set_speaker(SharedString::from(speaker));
+                    events::emit(Event::Line {
+                        speaker,
+                        text: plain,
+                    });
                     {
+                        let page_breaks = text_layout::page_breaks(&window, &text);
                         let mut send_text = self.text.write().unwrap();
-                        send_text.start_animation(text, window.get_text_speed());
+                        send_text.start_animation(text, window.get_text_speed(), page_breaks);
                     }
                     let tx = self.text_tx.clone().unwrap();
                     tx.try_send(self.text.clone())?;
@@ -539,6 +1097,20 @@ impl Executor {
                     self.execute_jump(Jump::Label(jump))?;
                 }
                 Command::Label => (),
+                Command::Nvl(nvl) => self.execute_nvl(nvl),
+                Command::Set { name, value } => self.set_variable(name, value),
+                Command::Textbox(action) => {
+                    let mut state = self.textbox.borrow().clone();
+                    match action {
+                        TextboxAction::Hide => state.hidden = true,
+                        TextboxAction::Show => state.hidden = false,
+                        TextboxAction::Style(style) if style == DEFAULT_STYLE => {
+                            state.style.clear()
+                        }
+                        TextboxAction::Style(style) => state.style = style,
+                    }
+                    self.set_textbox(state);
+                }
             }
         };
 
@@ -551,10 +1123,13 @@ impl Executor {
         if let Some(window) = weak.upgrade() {
             let volume = window.get_main_volume() / 100.0;
             let bgm_volume = window.get_bgm_volume() / 100.0;
-            self.media_player.borrow().play_bgm(
+            // 缺失的 BGM 只跳过，不中断剧情
+            if let Err(e) = self.media_player.borrow().play_bgm(
                 &format!("{}{}.ogg", ENGINE_CONFIG.bgm_path(), bgm),
                 volume * bgm_volume,
-            )?;
+            ) {
+                log::warn!("skip bgm `{bgm}`: {e}");
+            }
         }
 
         Ok(())
@@ -564,35 +1139,105 @@ impl Executor {
         &self,
         name: &String,
         voice: &String,
+    ) -> Result<Duration, EngineError> {
+        self.start_voice(name, voice, false)
+    }
+
+    /// 自动配音不要求写进 length.toml，没有记录时按解码出的时长等待。
+    fn play_auto_voice(&self, name: &String, voice: &String) -> Result<Duration, EngineError> {
+        self.start_voice(name, voice, true)
+    }
+
+    fn start_voice(
+        &self,
+        name: &String,
+        voice: &String,
+        auto: bool,
     ) -> Result<Duration, EngineError> {
         let weak = self.weak.clone();
+        let path = format!("{}/{}/{}.ogg", ENGINE_CONFIG.voice_path(), name, voice);
 
         if let (Some(length), Some(window)) = (VOICE_LENGTH.find(name), weak.upgrade()) {
             let volume = window.get_main_volume() / 100.0;
             let voice_volume = window.get_voice_volume() / 100.0;
             let character_volumes = window.get_character_volumes();
             {
-                let full_name = ENGINE_CONFIG.character_list().get(name).unwrap();
+                let Some(full_name) = ENGINE_CONFIG.character_list().get(name) else {
+                    log::warn!("skip voice `{voice}`: character `{name}` is not in [character]");
+                    return Ok(Duration::from_secs(0));
+                };
                 for CharacterVolume {
                     name: ch_name,
                     volume: ch_volume,
                 } in character_volumes.iter()
                 {
                     if ch_name == full_name {
-                        self.media_player.borrow().play_voice(
-                            &format!("{}/{}/{}.ogg", ENGINE_CONFIG.voice_path(), name, voice),
-                            volume * voice_volume * ch_volume / 100.0,
-                        )?;
+                        if let Err(e) = self
+                            .media_player
+                            .borrow()
+                            .play_voice(&path, volume * voice_volume * ch_volume / 100.0)
+                        {
+                            log::warn!("skip voice `{voice}`: {e}");
+                        } else {
+                            self.start_lip_sync(name);
+                        }
                         break;
                     }
                 }
             }
-            return Ok(*length.get(voice).unwrap());
+            return Ok(match length.get(voice) {
+                Some(length) => *length,
+                None if auto => *self
+                    .voice_durations
+                    .borrow_mut()
+                    .entry((name.to_string(), voice.to_string()))
+                    .or_insert_with(|| audio_duration(&path).unwrap_or_default()),
+                None => {
+                    log::warn!("voice `{voice}` has no length in {name}/length.toml");
+                    Duration::from_secs(0)
+                }
+            });
         }
 
         Ok(Duration::from_secs(0))
     }
 
+    fn start_lip_sync(&self, name: &str) {
+        let last = self
+            .lip_sync
+            .borrow_mut()
+            .replace(LipSync::new(name.to_string()));
+        if let Some(last) = last.filter(|last| last.name != name) {
+            self.set_mouth_frame(&last.name, 0);
+        }
+    }
+
+    /// 配音播放时按音量切换角色的口型，播完或被打断后回到闭嘴。
+    pub(crate) fn update_lip_sync(&self) {
+        let mut lip_sync = self.lip_sync.borrow_mut();
+        let Some(sync) = lip_sync.as_mut() else {
+            return;
+        };
+        let level = self.media_player.borrow().voice_level();
+        let frame = level.map(|level| sync.frame(level)).unwrap_or_default();
+        self.set_mouth_frame(&sync.name, frame);
+        if level.is_none() {
+            *lip_sync = None;
+        }
+    }
+
+    fn set_mouth_frame(&self, name: &str, frame: i32) {
+        for i in 0..self.figure_items.row_count() {
+            let Some(mut item) = self.figure_items.row_data(i) else {
+                continue;
+            };
+            if item.name == name && item.mouth_frame != frame {
+                item.mouth_frame = frame;
+                self.figure_items.set_row_data(i, item);
+            }
+        }
+    }
+
     fn show_bg(&mut self, bg: &Command) -> Result<(), EngineError> {
         let weak = self.weak.clone();
         let Command::Background {
@@ -609,17 +1254,17 @@ impl Executor {
         let path = if *is_cg {
             if let Some((index, _)) = CG_CONFIG.find_by_name(name) {
                 self.unlock(*index);
-                save_extra_config(self.cg.clone())?;
+                save_extra_config(self.cg.clone(), self.read.clone())?;
             }
             ENGINE_CONFIG.cg_path()
         } else {
             ENGINE_CONFIG.background_path()
         };
 
+        events::emit(Event::Background { name: name.clone() });
         if let Some(window) = weak.upgrade() {
-            let image = Image::load_from_path(Path::new(&format!("{path}{name}.png"))).unwrap();
             window.set_bg((
-                image,
+                load_image(&format!("{path}{name}.png")),
                 x_offset.unwrap_or(0.0),
                 y_offset.unwrap_or(0.0),
                 zoom.unwrap_or(1.0),
@@ -647,70 +1292,91 @@ impl Executor {
             return Ok(());
         }
 
-        if let (Some(body_para), Some(face_para), Some(offset)) = FIGURE_CONFIG.find(name) {
-            let rate = *body_para.get(body).unwrap();
-            let body_img = Image::load_from_path(Path::new(&format!(
-                "{}{}/{}/{}.png",
-                ENGINE_CONFIG.figure_path(),
-                name,
-                distance,
-                body
-            )))
-            .unwrap();
-            let (face_x, face_y) = face_para.get(face).unwrap();
-            let face_img = Image::load_from_path(Path::new(&format!(
-                "{}{}/{}/{}.png",
-                ENGINE_CONFIG.figure_path(),
-                name,
-                distance,
-                face
-            )))
-            .unwrap();
-
-            let (base_x, base_y, width_ratio) = parse_position(position, distance);
-
-            let model = self.figure_items.clone();
-            let mut found_idx = None;
-            for i in 0..model.row_count() {
-                let item = model.row_data(i).unwrap();
-                if item.name == name {
-                    found_idx = Some(i);
-                    break;
-                }
-            }
+        let figure_config = figure_config();
+        let (Some(body_para), Some(face_para), Some(offset)) = figure_config.find(name) else {
+            log::warn!("skip figure `{name}`: no figure config");
+            return Ok(());
+        };
+        let Some(rate) = body_para.get(body).copied() else {
+            log::warn!("skip figure `{name}`: body `{body}` is not in {name}.toml");
+            return Ok(());
+        };
+        let body_img = load_image(&format!(
+            "{}{}/{}/{}.png",
+            ENGINE_CONFIG.figure_path(),
+            name,
+            distance,
+            body
+        ));
+        let (face_x, face_y) = face_para.get(face).copied().unwrap_or_else(|| {
+            log::warn!("figure `{name}`: face `{face}` is not in {name}.toml");
+            (0.0, 0.0)
+        });
+        let face_img = load_image(&format!(
+            "{}{}/{}/{}.png",
+            ENGINE_CONFIG.figure_path(),
+            name,
+            distance,
+            face
+        ));
 
-            let id = if let Some(i) = found_idx {
-                model.row_data(i).unwrap().id
-            } else {
-                let mut id_counter = self.figure_id.borrow_mut();
-                *id_counter += 1;
-                *id_counter
-            };
+        self.faces
+            .borrow_mut()
+            .insert(name.to_string(), face_img.clone());
+        // 正在说话的角色换了表情时，侧边头像跟着换
+        if let Some(window) = self.weak.upgrade() {
+            let mut style = window.get_speaker_style();
+            if style.has_portrait && style.character == name {
+                style.portrait = face_img.clone();
+                window.set_speaker_style(style);
+            }
+        }
 
-            let item = FigureItem {
-                id,
-                name: name.to_shared_string(),
-                distance: distance.to_shared_string(),
-                body: body_img,
-                face: face_img,
-                rate,
-                offset: *offset,
-                face_x: *face_x,
-                face_y: *face_y,
-                base_x,
-                base_y,
-                x_offset: 0.0,
-                y_offset: 0.0,
-                width_ratio,
-            };
+        let (base_x, base_y, width_ratio) = parse_position(position, distance);
 
-            if let Some(i) = found_idx {
-                model.set_row_data(i, item);
-            } else {
-                model.push(item);
+        let model = self.figure_items.clone();
+        let mut found_idx = None;
+        for i in 0..model.row_count() {
+            let item = model.row_data(i).unwrap();
+            if item.name == name {
+                found_idx = Some(i);
+                break;
             }
         }
 
+        let id = if let Some(i) = found_idx {
+            model.row_data(i).unwrap().id
+        } else {
+            let mut id_counter = self.figure_id.borrow_mut();
+            *id_counter += 1;
+            *id_counter
+        };
+
+        let item = FigureItem {
+            id,
+            name: name.to_shared_string(),
+            distance: distance.to_shared_string(),
+            body: body_img,
+            face: face_img,
+            rate,
+            offset: *offset,
+            face_x,
+            face_y,
+            base_x,
+            base_y,
+            x_offset: 0.0,
+            y_offset: 0.0,
+            width_ratio,
+            mouths: load_mouths(&figure_config, name, distance, face),
+            mouth_frame: 0,
+        };
+
+        if let Some(i) = found_idx {
+            model.set_row_data(i, item);
+        } else {
+            model.push(item);
+        }
+
         Ok(())
     }
 
@@ -900,7 +1566,7 @@ impl Executor {
                     let executor = executor_for_finish.clone();
                     slint::spawn_local(async move {
                         if let Err(e) = executor.execute_stop_video().await {
-                            eprintln!("video auto-stop failed: {e}");
+                            logger::log_error("video auto-stop", &e);
                         }
                     })
                     .expect("video timer: no event loop");
@@ -931,6 +1597,203 @@ impl Executor {
         }
 
         let mut this = self.clone();
+        if self.splash.borrow().is_active() {
+            return this.execute_splash_next();
+        }
         this.execute_script()
     }
+
+    /// 应用剧本控制的对话框状态，样式没有变化时不重新加载边框图片。
+    fn set_textbox(&self, state: TextboxState) {
+        let Some(window) = self.weak.upgrade() else {
+            return;
+        };
+        window.set_textbox_hidden(state.hidden);
+        let mut current = self.textbox.borrow_mut();
+        if current.style != state.style {
+            window.set_textbox_style(TEXTBOX_THEME.style(&state.style));
+        }
+        *current = state;
+    }
+
+    fn execute_nvl(&self, nvl: NvlAction) {
+        let on = match (nvl, self.nvl.borrow().is_some()) {
+            (NvlAction::On, true) | (NvlAction::Off, false) => return,
+            (NvlAction::Clear, false) => {
+                log::warn!("`@nvl clear` outside NVL mode is ignored");
+                return;
+            }
+            (NvlAction::Off, true) => false,
+            (NvlAction::On | NvlAction::Clear, _) => true,
+        };
+        self.reset_nvl(on);
+    }
+
+    /// 切换 NVL 模式，开启时总是从新的一页开始。
+    fn reset_nvl(&self, on: bool) {
+        let Some(window) = self.weak.upgrade() else {
+            return;
+        };
+        let page = on.then(|| NvlPage::new(self.script.borrow().next_nvl_page()));
+        window.set_nvl_mode(on);
+        self.set_speaker(&window, "");
+        show_dialogue(&window, Frame::whole(String::new()));
+        nvl::show(&window, page.as_ref());
+        *self.nvl.borrow_mut() = page;
+    }
+
+    /// 显示说话人，按角色配置换成显示名并设置名牌颜色和侧边头像，返回显示的名字。
+    fn set_speaker(&self, window: &MainWindow, speaker: &str) -> SharedString {
+        let (name, style) =
+            speaker::resolve(speaker, &self.variables.borrow(), &self.faces.borrow());
+        window.set_speaker(name.clone());
+        window.set_speaker_style(style);
+        name
+    }
+
+    /// NVL 模式下把台词加到当前页，放不下时换页。返回所在页的编号。
+    fn push_nvl_line(&self, window: &MainWindow, speaker: &str, text: &str) -> Option<i32> {
+        let mut nvl = self.nvl.borrow_mut();
+        let page = nvl.as_mut()?;
+        if !page.fits(window, speaker, text) {
+            *page = NvlPage::new(self.script.borrow().next_nvl_page());
+        }
+        page.push(speaker.to_string(), text.to_string());
+        nvl::show(window, Some(page));
+        Some(page.id)
+    }
+
+    /// 窗口大小变化后重新排版对话框和 NVL 面板。
+    pub(crate) fn relayout_dialogue(&self) {
+        if let Some(window) = self.weak.upgrade() {
+            text_executor::relayout(&window);
+            nvl::show(&window, self.nvl.borrow().as_ref());
+        }
+    }
+
+    fn push_snapshot(&self) {
+        let Some(window) = self.weak.upgrade() else {
+            return;
+        };
+        let mut history = self.history.borrow_mut();
+        let script = self.script.borrow();
+        let backlog_len = script.backlog_len();
+        let voice = match history.current() {
+            Some(last) if last.backlog_len >= backlog_len => None,
+            _ => script.last_voice(),
+        };
+        let choices = if *self.choose_lock.borrow() {
+            window.get_choose_branch().iter().collect()
+        } else {
+            Vec::new()
+        };
+        history.push(Snapshot {
+            script: script.name().to_string(),
+            index: script.index(),
+            backlog_len,
+            explain: script.explain().to_string(),
+            bgm: script.current_bgm().to_string(),
+            bg: window.get_bg(),
+            figures: self.figure_items.iter().collect(),
+            speaker: window.get_speaker(),
+            speaker_style: window.get_speaker_style(),
+            text: self.text.read().unwrap().full_text.clone(),
+            voice,
+            choices,
+            nvl: self.nvl.borrow().clone(),
+            textbox: self.textbox.borrow().clone(),
+            variables: self.variables.borrow().clone(),
+        });
+    }
+
+    // 延时立绘在块执行后才到达，推进下一块前把它们补进当前快照
+    fn refresh_snapshot(&self) {
+        if let Some(snapshot) = self.history.borrow_mut().current() {
+            snapshot.figures = self.figure_items.iter().collect();
+        }
+    }
+
+    pub(crate) fn execute_rollback(&mut self) -> Result<(), EngineError> {
+        if self.video_context.borrow().get_video_player_ref().is_some() {
+            return Ok(());
+        }
+        let (snapshot, chosen) = {
+            let mut history = self.history.borrow_mut();
+            let Some(snapshot) = history.step_back() else {
+                return Ok(());
+            };
+            (snapshot, history.chosen().cloned())
+        };
+
+        self.delay_channels.as_ref().unwrap().clear_all();
+        {
+            let mut script = self.script.borrow_mut();
+            if snapshot.script != script.name() {
+                let backlog = script.to_owned().take_backlog();
+                let mut scr = Parser::load(&snapshot.script)?;
+                scr.set_backlog(backlog);
+                *script = scr;
+            }
+            script.restore(
+                snapshot.index,
+                snapshot.backlog_len,
+                snapshot.bgm.clone(),
+                snapshot.explain.clone(),
+            );
+        }
+
+        if snapshot.bgm.is_empty() {
+            self.media_player.borrow().stop_bgm();
+        } else {
+            self.play_bgm(snapshot.bgm.clone())?;
+        }
+
+        self.clean_fg("All")?;
+        {
+            let mut faces = self.faces.borrow_mut();
+            for mut figure in snapshot.figures {
+                faces.insert(figure.name.to_string(), figure.face.clone());
+                figure.mouth_frame = 0;
+                self.figure_items.push(figure);
+            }
+        }
+        *self.variables.borrow_mut() = snapshot.variables;
+
+        if let Some(window) = self.weak.upgrade() {
+            window.set_bg(snapshot.bg);
+            window.set_speaker(snapshot.speaker);
+            window.set_speaker_style(snapshot.speaker_style);
+            window.set_is_read(true);
+            window.set_nvl_mode(snapshot.nvl.is_some());
+            nvl::show(&window, snapshot.nvl.as_ref());
+            *self.nvl.borrow_mut() = snapshot.nvl.clone();
+            self.set_textbox(snapshot.textbox.clone());
+
+            let mut choices = snapshot.choices;
+            if let (false, Some(chosen)) = (ENGINE_CONFIG.allow_rechoose(), &chosen) {
+                choices.retain(|(_, text)| text == chosen);
+                for (index, choice) in choices.iter_mut().enumerate() {
+                    choice.0 = index as i32;
+                }
+            }
+            *self.choose_lock.borrow_mut() = !choices.is_empty();
+            window.set_current_choose(choices.len() as i32);
+            window.set_choose_branch(Rc::new(VecModel::from(choices)).into());
+        }
+        {
+            let page_breaks = match self.weak.upgrade() {
+                Some(window) => text_layout::page_breaks(&window, &snapshot.text),
+                None => Vec::new(),
+            };
+            let mut text = self.text.write().unwrap();
+            text.show_full(snapshot.text, page_breaks);
+        }
+        self.text_tx.clone().unwrap().try_send(self.text.clone())?;
+
+        if let Some((name, voice)) = snapshot.voice {
+            self.play_voice(&name, &voice)?;
+        }
+
+        Ok(())
+    }
 }
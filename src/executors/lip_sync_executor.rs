@@ -0,0 +1,54 @@
+use crate::executors::executor::Executor;
+use std::time::Duration;
+
+/// 比这更小的音量视为没有声音。
+const SILENCE: f32 = 0.01;
+
+/// 正在对口型的角色，以及这句配音到目前为止的最大音量。
+pub(crate) struct LipSync {
+    pub(crate) name: String,
+    peak: f32,
+}
+
+impl LipSync {
+    pub(crate) fn new(name: String) -> Self {
+        LipSync { name, peak: 0.0 }
+    }
+
+    /// 按相对最大音量的比例选口型：0 闭嘴、1 半张、2 张嘴。
+    pub(crate) fn frame(&mut self, level: f32) -> i32 {
+        self.peak = self.peak.max(level);
+        if level < SILENCE {
+            return 0;
+        }
+        match level / self.peak {
+            ratio if ratio < 0.3 => 0,
+            ratio if ratio < 0.6 => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// 配音播放时定时读取音量，切换说话角色的口型。
+pub(crate) struct LipSyncExecutor {
+    timer: slint::Timer,
+    executor: Executor,
+}
+
+impl LipSyncExecutor {
+    pub(crate) fn new(executor: Executor) -> Self {
+        LipSyncExecutor {
+            timer: slint::Timer::default(),
+            executor,
+        }
+    }
+
+    pub(crate) fn start_timer(&mut self) {
+        let executor = self.executor.clone();
+        self.timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_millis(40),
+            move || executor.update_lip_sync(),
+        );
+    }
+}
@@ -1,25 +1,43 @@
+use crate::config::args::ARGS;
 use crate::error::EngineError;
 use crate::executors::{
     auto_executor::AutoExecutor, delay_executor::DelayExecutor, executor::Executor,
-    skip_executor::SkipExecutor, text_executor::TextExecutor,
+    lip_sync_executor::LipSyncExecutor, reload_executor::ReloadExecutor,
+    replay_executor::ReplayExecutor, skip_executor::SkipExecutor, text_executor::TextExecutor,
 };
 use tokio::sync::mpsc::Sender;
 
 pub(crate) mod auto_executor;
+pub(crate) mod console;
+pub(crate) mod crash;
 pub(crate) mod delay_executor;
+pub(crate) mod events;
 pub(crate) mod executor;
+pub(crate) mod lip_sync_executor;
+pub(crate) mod nvl;
+pub(crate) mod record;
+pub(crate) mod reload_executor;
+pub(crate) mod replay_executor;
+pub(crate) mod rollback;
 pub(crate) mod skip_executor;
+pub(crate) mod speaker;
 pub(crate) mod text_executor;
+pub(crate) mod text_layout;
+pub(crate) mod title;
 
 pub(crate) struct ExecutorTX {
     auto_tx: Sender<()>,
     skip_tx: Sender<()>,
+    hold_tx: Sender<bool>,
     _text_executor: TextExecutor,
     _auto_executor: AutoExecutor,
     _skip_executor: SkipExecutor,
     _delay_executor: DelayExecutor,
     _delay_move_executor: DelayExecutor,
     _loop_move_executor: DelayExecutor,
+    _lip_sync_executor: LipSyncExecutor,
+    _reload_executor: Option<ReloadExecutor>,
+    _replay_executor: Option<ReplayExecutor>,
 }
 
 impl ExecutorTX {
@@ -30,6 +48,10 @@ impl ExecutorTX {
     pub(crate) fn skip_tx(&self) -> Sender<()> {
         self.skip_tx.clone()
     }
+
+    pub(crate) fn hold_tx(&self) -> Sender<bool> {
+        self.hold_tx.clone()
+    }
 }
 
 pub(crate) fn load_data(executor: &mut Executor) -> Result<ExecutorTX, EngineError> {
@@ -62,7 +84,8 @@ pub(crate) fn load_data(executor: &mut Executor) -> Result<ExecutorTX, EngineErr
     executor.set_auto_tx(auto_delay_tx.clone());
     auto_executor.executor.set_auto_tx(auto_delay_tx);
 
-    let (mut skip_executor, skip_tx) = SkipExecutor::new(executor.clone());
+    let (mut skip_executor, skip_tx, hold_tx) = SkipExecutor::new(executor.clone());
+    let mut lip_sync_executor = LipSyncExecutor::new(executor.clone());
 
     text_executor.start_timer();
     delay_executor.start_timer();
@@ -70,22 +93,54 @@ pub(crate) fn load_data(executor: &mut Executor) -> Result<ExecutorTX, EngineErr
     loop_move_executor.start_timer();
     auto_executor.start_timer();
     skip_executor.start_timer();
+    lip_sync_executor.start_timer();
 
     executor.load_save_data()?;
     executor.load_volume();
     executor.load_character_volumes();
     executor.load_auto();
     executor.load_text();
-    executor.load_extra();
+    executor.load_extra()?;
+
+    let reload_executor = ARGS.dev().then(|| {
+        let mut reload_executor = ReloadExecutor::new(executor.clone());
+        reload_executor.start_timer();
+        reload_executor
+    });
+
+    // 回放时不再录制
+    let replay_executor = match (ARGS.replay(), ARGS.record()) {
+        (Some(path), _) => {
+            let inputs = record::load_replay(path)?;
+            let mut replay_executor = ReplayExecutor::new(
+                executor.clone(),
+                inputs,
+                auto_tx.clone(),
+                skip_tx.clone(),
+                hold_tx.clone(),
+            );
+            replay_executor.start_timer();
+            Some(replay_executor)
+        }
+        (None, Some(path)) => {
+            record::start_recording(path)?;
+            None
+        }
+        (None, None) => None,
+    };
 
     Ok(ExecutorTX {
         auto_tx,
         skip_tx,
+        hold_tx,
         _text_executor: text_executor,
         _auto_executor: auto_executor,
         _skip_executor: skip_executor,
         _delay_executor: delay_executor,
         _delay_move_executor: delay_move_executor,
         _loop_move_executor: loop_move_executor,
+        _lip_sync_executor: lip_sync_executor,
+        _reload_executor: reload_executor,
+        _replay_executor: replay_executor,
     })
 }
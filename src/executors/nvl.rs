@@ -0,0 +1,58 @@
+use crate::executors::{text_executor::lines_model, text_layout};
+use crate::ui::initialize::{MainWindow, NvlEntry};
+use slint::VecModel;
+use std::rc::Rc;
+
+/// NVL 面板一页最多的行数，说话人也占一行。
+pub(crate) const NVL_LINES: usize = 16;
+
+/// NVL 模式下的当前页，最后一条是正在打字的台词。
+#[derive(Debug, Clone)]
+pub(crate) struct NvlPage {
+    pub(crate) id: i32,
+    entries: Vec<(String, String)>,
+}
+
+impl NvlPage {
+    pub(crate) fn new(id: i32) -> Self {
+        NvlPage {
+            id,
+            entries: Vec::new(),
+        }
+    }
+
+    /// 当前页还能否放下这条台词，空页总能放下。
+    pub(crate) fn fits(&self, window: &MainWindow, speaker: &str, text: &str) -> bool {
+        let used: usize = self
+            .entries
+            .iter()
+            .map(|(speaker, text)| entry_lines(window, speaker, text))
+            .sum();
+        self.entries.is_empty() || used + entry_lines(window, speaker, text) <= NVL_LINES
+    }
+
+    pub(crate) fn push(&mut self, speaker: String, text: String) {
+        self.entries.push((speaker, text));
+    }
+}
+
+fn entry_lines(window: &MainWindow, speaker: &str, text: &str) -> usize {
+    text_layout::layout(window, text).0.len() + usize::from(!speaker.is_empty())
+}
+
+/// 显示当前页已经读完的台词，正在打字的一条由 dialogue-lines 显示。
+pub(crate) fn show(window: &MainWindow, page: Option<&NvlPage>) {
+    let entries: Vec<NvlEntry> = page
+        .map(|page| &page.entries[..page.entries.len().saturating_sub(1)])
+        .unwrap_or_default()
+        .iter()
+        .map(|(speaker, text)| {
+            let (lines, _) = text_layout::layout(window, text);
+            NvlEntry {
+                speaker: speaker.into(),
+                lines: lines_model(&lines.iter().collect::<Vec<_>>(), usize::MAX),
+            }
+        })
+        .collect();
+    window.set_nvl_entries(Rc::new(VecModel::from(entries)).into());
+}
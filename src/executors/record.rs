@@ -0,0 +1,405 @@
+use crate::error::{EngineError, ExecutorError, SaveError};
+use crate::executors::events::Event;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{LineWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// 录制文件的首行，版本不同的文件不做回放。
+const HEADER: &str = "# rusteng record v2";
+/// 回放时最多逐条输出的不一致事件数。
+const MISMATCH_LOG_LIMIT: usize = 10;
+
+/// 录制的玩家输入。
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Input {
+    NewGame,
+    Click,
+    Choose(usize),
+    /// `source` 为真是切换，为假是停止；`on` 是 UI 切换后的状态，回放时先设回窗口。
+    Auto {
+        source: bool,
+        on: bool,
+    },
+    Skip {
+        source: bool,
+        on: bool,
+    },
+    FastForward(bool),
+    Save {
+        page: i32,
+        index: i32,
+    },
+    Load {
+        page: i32,
+        index: i32,
+    },
+    Rollback,
+    BacklogJump {
+        script: String,
+        index: i32,
+    },
+    StopVideo,
+    SplashSkip,
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn toggle(source: bool) -> &'static str {
+    if source {
+        "toggle"
+    } else {
+        "stop"
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::NewGame => write!(f, "new_game"),
+            Input::Click => write!(f, "click"),
+            Input::Choose(index) => write!(f, "choose {index}"),
+            Input::Auto { source, on } => write!(f, "auto {} {}", toggle(*source), on_off(*on)),
+            Input::Skip { source, on } => write!(f, "skip {} {}", toggle(*source), on_off(*on)),
+            Input::FastForward(hold) => write!(f, "fast_forward {}", on_off(*hold)),
+            Input::Save { page, index } => write!(f, "save {page} {index}"),
+            Input::Load { page, index } => write!(f, "load {page} {index}"),
+            Input::Rollback => write!(f, "rollback"),
+            Input::BacklogJump { script, index } => write!(f, "backlog_jump {index} {script}"),
+            Input::StopVideo => write!(f, "stop_video"),
+            Input::SplashSkip => write!(f, "splash_skip"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<Input, ()> {
+        // 剧本名可能带空格，放在最后原样取出
+        if let Some(rest) = line.strip_prefix("backlog_jump ") {
+            let (index, script) = rest.split_once(' ').ok_or(())?;
+            return Ok(Input::BacklogJump {
+                script: script.to_string(),
+                index: index.parse().map_err(|_| ())?,
+            });
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let switch = |word: &str| match word {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(()),
+        };
+        let source = |word: &str| match word {
+            "toggle" => Ok(true),
+            "stop" => Ok(false),
+            _ => Err(()),
+        };
+        let number = |word: &str| word.parse::<i32>().map_err(|_| ());
+        Ok(match words.as_slice() {
+            ["new_game"] => Input::NewGame,
+            ["click"] => Input::Click,
+            ["choose", index] => Input::Choose(index.parse().map_err(|_| ())?),
+            ["auto", from, on] => Input::Auto {
+                source: source(from)?,
+                on: switch(on)?,
+            },
+            ["skip", from, on] => Input::Skip {
+                source: source(from)?,
+                on: switch(on)?,
+            },
+            ["fast_forward", hold] => Input::FastForward(switch(hold)?),
+            ["save", page, index] => Input::Save {
+                page: number(page)?,
+                index: number(index)?,
+            },
+            ["load", page, index] => Input::Load {
+                page: number(page)?,
+                index: number(index)?,
+            },
+            ["rollback"] => Input::Rollback,
+            ["stop_video"] => Input::StopVideo,
+            ["splash_skip"] => Input::SplashSkip,
+            _ => return Err(()),
+        })
+    }
+}
+
+enum Session {
+    Record {
+        file: LineWriter<File>,
+        start: Instant,
+    },
+    Replay {
+        path: PathBuf,
+        expected: VecDeque<String>,
+        actual: Vec<String>,
+        mismatches: usize,
+    },
+}
+
+thread_local! {
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+/// 开始录制，输入和事件都按相对时间写进同一个文件。
+pub(crate) fn start_recording(path: &Path) -> Result<(), EngineError> {
+    let write_error = |e| SaveError::Write {
+        path: path.display().to_string(),
+        source: e,
+    };
+    let mut file = LineWriter::new(File::create(path).map_err(write_error)?);
+    writeln!(file, "{HEADER}").map_err(write_error)?;
+    log::info!("recording input to {}", path.display());
+    SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session::Record {
+            file,
+            start: Instant::now(),
+        })
+    });
+    Ok(())
+}
+
+fn write_entry(file: &mut LineWriter<File>, start: &Instant, kind: char, line: &dyn fmt::Display) {
+    let elapsed = start.elapsed().as_millis();
+    if let Err(e) = writeln!(file, "{elapsed} {kind} {line}") {
+        log::warn!("failed to write record: {e}");
+    }
+}
+
+pub(crate) fn record_input(input: Input) {
+    SESSION.with(|session| {
+        if let Some(Session::Record { file, start }) = session.borrow_mut().as_mut() {
+            write_entry(file, start, '>', &input);
+        }
+    });
+}
+
+/// 录制时写入事件，回放时和录制的事件逐条比对。
+pub(crate) fn record_event(event: &Event) {
+    SESSION.with(|session| match session.borrow_mut().as_mut() {
+        Some(Session::Record { file, start }) => write_entry(file, start, '=', event),
+        Some(Session::Replay {
+            expected,
+            actual,
+            mismatches,
+            ..
+        }) => {
+            let line = event.to_string();
+            match expected.pop_front() {
+                Some(expect) if expect == line => {}
+                expect => {
+                    *mismatches += 1;
+                    if *mismatches <= MISMATCH_LOG_LIMIT {
+                        log::error!(
+                            "replay mismatch at event #{}: expected `{}`, got `{line}`",
+                            actual.len() + 1,
+                            expect.as_deref().unwrap_or("end of recording")
+                        );
+                    }
+                }
+            }
+            actual.push(line);
+        }
+        None => {}
+    });
+}
+
+/// 读取录制文件，返回按时间排好的输入，录制的事件留作比对。
+pub(crate) fn load_replay(path: &Path) -> Result<VecDeque<(Duration, Input)>, EngineError> {
+    let content = fs::read_to_string(path).map_err(|e| SaveError::Read {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    let invalid = |line: usize, content: &str| ExecutorError::InvalidRecord {
+        path: path.display().to_string(),
+        line,
+        content: content.to_string(),
+    };
+
+    let mut lines = content.lines().enumerate();
+    if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+        return Err(invalid(1, content.lines().next().unwrap_or_default()).into());
+    }
+    let mut inputs = VecDeque::new();
+    let mut expected = VecDeque::new();
+    for (number, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed = line
+            .split_once(' ')
+            .and_then(|(time, rest)| Some((time.parse::<u64>().ok()?, rest.split_once(' ')?)));
+        match parsed {
+            Some((time, (">", input))) => {
+                let input = input.parse().map_err(|_| invalid(number + 1, line))?;
+                inputs.push_back((Duration::from_millis(time), input));
+            }
+            Some((_, ("=", event))) => expected.push_back(event.to_string()),
+            _ => return Err(invalid(number + 1, line).into()),
+        }
+    }
+
+    log::info!(
+        "replaying {} inputs from {}, expecting {} events",
+        inputs.len(),
+        path.display(),
+        expected.len()
+    );
+    SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session::Replay {
+            path: path.to_path_buf(),
+            expected,
+            actual: Vec::new(),
+            mismatches: 0,
+        })
+    });
+    Ok(inputs)
+}
+
+/// 结束录制或回放。回放时把实际事件写到 `<录制文件>.actual` 方便对比，
+/// 和录制不一致时返回错误，进程以非零状态退出。
+pub(crate) fn finish() -> Result<(), EngineError> {
+    let Some(session) = SESSION.with(|session| session.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let Session::Replay {
+        path,
+        expected,
+        actual,
+        mut mismatches,
+    } = session
+    else {
+        return Ok(());
+    };
+
+    mismatches += expected.len();
+    let mut actual_path = path.into_os_string();
+    actual_path.push(".actual");
+    let actual_path = PathBuf::from(actual_path);
+    let mut content = actual.join("\n");
+    content.push('\n');
+    fs::write(&actual_path, content).map_err(|e| SaveError::Write {
+        path: actual_path.display().to_string(),
+        source: e,
+    })?;
+
+    if mismatches > 0 {
+        return Err(ExecutorError::ReplayMismatch(mismatches).into());
+    }
+    log::info!("replay finished, {} events matched", actual.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_record(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rusteng-{}-{name}.rec", std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn input_round_trip() {
+        let inputs = [
+            Input::NewGame,
+            Input::Click,
+            Input::Choose(2),
+            Input::Auto {
+                source: true,
+                on: true,
+            },
+            Input::Skip {
+                source: false,
+                on: true,
+            },
+            Input::FastForward(false),
+            Input::Save { page: 1, index: 3 },
+            Input::Load { page: 0, index: 11 },
+            Input::Rollback,
+            Input::BacklogJump {
+                script: "chapter 1 end".to_string(),
+                index: 7,
+            },
+            Input::StopVideo,
+            Input::SplashSkip,
+        ];
+        for input in inputs {
+            assert_eq!(input.to_string().parse(), Ok(input));
+        }
+    }
+
+    #[test]
+    fn input_rejects_malformed() {
+        for line in [
+            "",
+            "jump",
+            "choose x",
+            "auto on",
+            "skip toggle maybe",
+            "save 1",
+            "backlog_jump story",
+            "backlog_jump x story",
+        ] {
+            assert_eq!(line.parse::<Input>(), Err(()), "{line}");
+        }
+    }
+
+    #[test]
+    fn load_replay_reads_inputs() {
+        let path = write_record(
+            "ok",
+            &format!("{HEADER}\n0 > new_game\n\n1500 = bgm main\n2000 > backlog_jump 3 a b\n"),
+        );
+        let inputs = load_replay(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Vec::from(inputs),
+            vec![
+                (Duration::ZERO, Input::NewGame),
+                (
+                    Duration::from_millis(2000),
+                    Input::BacklogJump {
+                        script: "a b".to_string(),
+                        index: 3,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_replay_rejects_bad_lines() {
+        for (name, content, bad_line) in [
+            ("header", "# rusteng record v0\n0 > click\n".to_string(), 1),
+            ("empty", String::new(), 1),
+            ("input", format!("{HEADER}\n0 > click\n10 > fly\n"), 3),
+            ("time", format!("{HEADER}\nsoon > click\n"), 2),
+            ("kind", format!("{HEADER}\n0 ? click\n"), 2),
+        ] {
+            let path = write_record(name, &content);
+            let result = load_replay(&path);
+            fs::remove_file(&path).unwrap();
+            match result {
+                Err(EngineError::Executor(ExecutorError::InvalidRecord { line, .. })) => {
+                    assert_eq!(line, bad_line, "{name}")
+                }
+                other => panic!("{name}: expected InvalidRecord, got {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::config::{figure, ENGINE_CONFIG};
+use crate::executors::executor::Executor;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+type Stamps = HashMap<PathBuf, SystemTime>;
+
+/// 递归收集目录下指定扩展名文件的修改时间。
+fn scan(dir: &Path, extensions: &[&str], stamps: &mut Stamps) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            scan(&path, extensions, stamps);
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.contains(&ext))
+        {
+            if let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) {
+                stamps.insert(path, modified);
+            }
+        }
+    }
+}
+
+fn scan_scripts() -> Stamps {
+    let mut stamps = Stamps::new();
+    scan(
+        Path::new(ENGINE_CONFIG.script_path()),
+        &["reg"],
+        &mut stamps,
+    );
+    stamps
+}
+
+fn scan_figures() -> Stamps {
+    let mut stamps = Stamps::new();
+    scan(
+        Path::new(ENGINE_CONFIG.figure_path()),
+        &["toml", "png"],
+        &mut stamps,
+    );
+    stamps
+}
+
+/// 有改动（新增、修改或删除）的文件。
+fn changed(old: &Stamps, new: &Stamps) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = new
+        .iter()
+        .filter(|(path, modified)| old.get(*path) != Some(*modified))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(old.keys().filter(|path| !new.contains_key(*path)).cloned());
+    changed
+}
+
+struct Watched {
+    scripts: Stamps,
+    figures: Stamps,
+    // 当前剧本改动了但还不能重载（不在剧情画面、正在选择等）
+    script_pending: bool,
+}
+
+/// 开发模式下轮询剧本和立绘目录，改动后重新加载当前剧本和立绘配置。
+pub(crate) struct ReloadExecutor {
+    timer: slint::Timer,
+    executor: Executor,
+    watched: Rc<RefCell<Watched>>,
+}
+
+impl ReloadExecutor {
+    pub(crate) fn new(executor: Executor) -> Self {
+        ReloadExecutor {
+            timer: slint::Timer::default(),
+            executor,
+            watched: Rc::new(RefCell::new(Watched {
+                scripts: scan_scripts(),
+                figures: scan_figures(),
+                script_pending: false,
+            })),
+        }
+    }
+
+    pub(crate) fn start_timer(&mut self) {
+        let mut executor = self.executor.clone();
+        let watched = self.watched.clone();
+        log::info!("dev mode: watching scripts and figures for changes");
+
+        self.timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_secs(1),
+            move || {
+                let mut watched = watched.borrow_mut();
+
+                let figures = scan_figures();
+                if !changed(&watched.figures, &figures).is_empty() {
+                    watched.figures = figures;
+                    match figure::reload() {
+                        Ok(()) => {
+                            log::info!("figure config reloaded");
+                            executor.execute_reload_figures();
+                        }
+                        Err(e) => executor.report_error("重载立绘配置", e.into()),
+                    }
+                }
+
+                let scripts = scan_scripts();
+                let current = executor.script_name();
+                let changed = changed(&watched.scripts, &scripts);
+                watched.scripts = scripts;
+                if changed
+                    .iter()
+                    .any(|path| path.file_stem().is_some_and(|stem| *stem == *current))
+                {
+                    watched.script_pending = true;
+                }
+                for path in changed {
+                    log::debug!("script changed: {}", path.display());
+                }
+
+                if watched.script_pending && executor.can_reload_script() {
+                    watched.script_pending = false;
+                    match executor.execute_reload_script() {
+                        Ok(()) => log::info!("script `{current}` reloaded"),
+                        Err(e) => executor.report_error("重载剧本", e),
+                    }
+                }
+            },
+        );
+    }
+}
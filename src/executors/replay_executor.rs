@@ -0,0 +1,163 @@
+use crate::error::EngineError;
+use crate::executors::{executor::Executor, record::Input};
+use slint::{ComponentHandle, Model};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::Sender;
+
+/// 最后一个输入之后再等一会儿，让延时命令和文字动画跑完。
+const SETTLE: Duration = Duration::from_secs(3);
+
+struct Progress {
+    inputs: VecDeque<(Duration, Input)>,
+    start: Instant,
+    finished_at: Option<Instant>,
+}
+
+/// 按录制时的时间点重放玩家输入，全部放完后退出。
+pub(crate) struct ReplayExecutor {
+    timer: slint::Timer,
+    executor: Executor,
+    progress: Rc<RefCell<Progress>>,
+    auto_tx: Sender<()>,
+    skip_tx: Sender<()>,
+    hold_tx: Sender<bool>,
+}
+
+impl ReplayExecutor {
+    pub(crate) fn new(
+        executor: Executor,
+        inputs: VecDeque<(Duration, Input)>,
+        auto_tx: Sender<()>,
+        skip_tx: Sender<()>,
+        hold_tx: Sender<bool>,
+    ) -> Self {
+        ReplayExecutor {
+            timer: slint::Timer::default(),
+            executor,
+            progress: Rc::new(RefCell::new(Progress {
+                inputs,
+                start: Instant::now(),
+                finished_at: None,
+            })),
+            auto_tx,
+            skip_tx,
+            hold_tx,
+        }
+    }
+
+    pub(crate) fn start_timer(&mut self) {
+        let mut executor = self.executor.clone();
+        let progress = self.progress.clone();
+        let (auto_tx, skip_tx, hold_tx) = (
+            self.auto_tx.clone(),
+            self.skip_tx.clone(),
+            self.hold_tx.clone(),
+        );
+        progress.borrow_mut().start = Instant::now();
+
+        self.timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_millis(10),
+            move || {
+                let mut progress = progress.borrow_mut();
+                let elapsed = progress.start.elapsed();
+                while progress
+                    .inputs
+                    .front()
+                    .is_some_and(|(time, _)| *time <= elapsed)
+                {
+                    let Some((_, input)) = progress.inputs.pop_front() else {
+                        break;
+                    };
+                    log::debug!("replay input: {input}");
+                    let result = match input {
+                        Input::NewGame => executor.execute_new_game(),
+                        Input::Click => executor.execute_script(),
+                        Input::Choose(index) => executor.execute_choose_index(index),
+                        Input::Auto { source, on } => {
+                            if let Some(window) = executor.get_weak().upgrade() {
+                                window.set_is_auto(on);
+                            }
+                            executor.execute_auto(auto_tx.clone(), source)
+                        }
+                        Input::Skip { source, on } => {
+                            if let Some(window) = executor.get_weak().upgrade() {
+                                window.set_is_skip(on);
+                            }
+                            executor.execute_skip(skip_tx.clone(), source)
+                        }
+                        Input::FastForward(hold) => {
+                            executor.execute_fast_forward(hold_tx.clone(), hold)
+                        }
+                        Input::Save { page, index } => executor.execute_save(index, page),
+                        Input::Load { page, index } => executor.execute_load_slot(index, page),
+                        Input::Rollback => executor.execute_rollback(),
+                        Input::BacklogJump { script, index } => {
+                            executor.execute_backlog_jump(script, index)
+                        }
+                        Input::StopVideo => {
+                            let executor = executor.clone();
+                            spawn_input(async move {
+                                if let Err(e) = executor.execute_stop_video().await {
+                                    executor.report_error("停止视频", e);
+                                }
+                            })
+                        }
+                        Input::SplashSkip => {
+                            let mut executor = executor.clone();
+                            spawn_input(async move {
+                                if let Err(e) = executor.execute_splash_skip().await {
+                                    executor.report_error("跳过", e);
+                                }
+                            })
+                        }
+                    };
+                    if let Err(e) = result {
+                        executor.report_error("回放输入", e);
+                    }
+                }
+
+                if progress.inputs.is_empty() {
+                    let finished_at = *progress.finished_at.get_or_insert_with(Instant::now);
+                    if finished_at.elapsed() >= SETTLE {
+                        if let Some(window) = executor.get_weak().upgrade() {
+                            let _ = window.hide();
+                        }
+                        let _ = slint::quit_event_loop();
+                    }
+                }
+            },
+        );
+    }
+}
+
+/// 异步的输入放到事件循环里跑，不阻塞计时器。
+fn spawn_input(future: impl Future<Output = ()> + 'static) -> Result<(), EngineError> {
+    if let Err(e) = slint::spawn_local(future) {
+        log::warn!("replay: failed to spawn input: {e}");
+    }
+    Ok(())
+}
+
+impl Executor {
+    /// 按序号选择当前的选择支，序号超出范围时忽略。
+    fn execute_choose_index(&mut self, index: usize) -> Result<(), EngineError> {
+        let choice = self
+            .get_weak()
+            .upgrade()
+            .and_then(|window| window.get_choose_branch().row_data(index));
+        match choice {
+            Some((_, choice)) => self.execute_choose(choice),
+            None => {
+                log::warn!("replay: no choice #{index} on screen");
+                Ok(())
+            }
+        }
+    }
+}
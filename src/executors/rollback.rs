@@ -0,0 +1,86 @@
+use crate::executors::nvl::NvlPage;
+use crate::script::TextboxState;
+use crate::ui::initialize::{FigureItem, SpeakerStyle};
+use slint::{Image, SharedString};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+pub(crate) type Background = (Image, f32, f32, f32);
+
+/// 执行完一个剧情块后的完整画面状态，回滚时原样恢复。
+#[derive(Clone)]
+pub(crate) struct Snapshot {
+    pub(crate) script: String,
+    pub(crate) index: usize,
+    pub(crate) backlog_len: usize,
+    pub(crate) explain: String,
+    pub(crate) bgm: String,
+    pub(crate) bg: Background,
+    pub(crate) figures: Vec<FigureItem>,
+    pub(crate) speaker: SharedString,
+    pub(crate) speaker_style: SpeakerStyle,
+    pub(crate) text: String,
+    pub(crate) voice: Option<(String, String)>,
+    pub(crate) choices: Vec<(i32, SharedString)>,
+    pub(crate) nvl: Option<NvlPage>,
+    pub(crate) textbox: TextboxState,
+    pub(crate) variables: BTreeMap<String, String>,
+}
+
+/// 有上限的快照栈，超出上限时丢弃最早的快照。
+/// 另外按（剧本, 块号）记住每个选择支选过的项，回滚越过选择支后依然有效。
+pub(crate) struct History {
+    limit: usize,
+    snapshots: VecDeque<Snapshot>,
+    chosen: HashMap<(String, usize), SharedString>,
+}
+
+impl History {
+    pub(crate) fn new(limit: usize) -> Self {
+        History {
+            limit,
+            snapshots: VecDeque::with_capacity(limit),
+            chosen: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, snapshot: Snapshot) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.limit {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// 丢弃当前快照，返回上一个快照（它留在栈顶作为新的当前状态）。
+    pub(crate) fn step_back(&mut self) -> Option<Snapshot> {
+        if self.snapshots.len() < 2 {
+            return None;
+        }
+        self.snapshots.pop_back();
+        self.snapshots.back().cloned()
+    }
+
+    pub(crate) fn current(&mut self) -> Option<&mut Snapshot> {
+        self.snapshots.back_mut()
+    }
+
+    /// 当前选择支之前选过的项。
+    pub(crate) fn chosen(&self) -> Option<&SharedString> {
+        let snapshot = self.snapshots.back()?;
+        self.chosen.get(&(snapshot.script.clone(), snapshot.index))
+    }
+
+    pub(crate) fn record_choice(&mut self, choice: SharedString) {
+        if let Some(snapshot) = self.snapshots.back() {
+            let key = (snapshot.script.clone(), snapshot.index);
+            self.chosen.insert(key, choice);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.snapshots.clear();
+        self.chosen.clear();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::executors::executor::Executor;
+use crate::ui::initialize::MainWindow;
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -10,40 +11,48 @@ use tokio::sync::mpsc::{channel, Sender};
 
 pub(crate) struct SkipExecutor {
     timer: slint::Timer,
+    hold_timer: slint::Timer,
     pub(crate) executor: Executor,
     is_skip: Arc<AtomicBool>,
+    is_hold: Arc<AtomicBool>,
 }
 
 impl SkipExecutor {
-    pub(crate) fn new(executor: Executor) -> (Self, Sender<()>) {
+    pub(crate) fn new(executor: Executor) -> (Self, Sender<()>, Sender<bool>) {
         let (tx, mut rx) = channel::<()>(10);
+        let (hold_tx, mut hold_rx) = channel::<bool>(10);
         let is_skip = Arc::new(AtomicBool::new(false));
+        let is_hold = Arc::new(AtomicBool::new(false));
 
         // 创建定时器
         let timer = slint::Timer::default();
+        let hold_timer = slint::Timer::default();
 
         let executor = Self {
             timer,
+            hold_timer,
             executor,
             is_skip: is_skip.clone(),
+            is_hold: is_hold.clone(),
         };
 
-        // 监听控制信号的任务
+        // 每个信号翻转一次快进状态；定时器遇到停止条件时也会直接关掉它
         let is_skip_clone = is_skip.clone();
         tokio::spawn(async move {
-            let mut start = true;
             while (rx.recv().await).is_some() {
-                if start {
-                    is_skip_clone.store(true, Ordering::Relaxed);
-                    start = false;
-                } else {
-                    is_skip_clone.store(false, Ordering::Relaxed);
-                    start = true;
-                }
+                let skip = is_skip_clone.load(Ordering::Relaxed);
+                is_skip_clone.store(!skip, Ordering::Relaxed);
+            }
+        });
+
+        // 按住 Ctrl 时快进，松开即停
+        tokio::spawn(async move {
+            while let Some(hold) = hold_rx.recv().await {
+                is_hold.store(hold, Ordering::Relaxed);
             }
         });
 
-        (executor, tx)
+        (executor, tx, hold_tx)
     }
 
     pub(crate) fn start_timer(&mut self) {
@@ -54,16 +63,60 @@ impl SkipExecutor {
             slint::TimerMode::Repeated,
             Duration::from_millis(100),
             move || {
-                if is_skip.load(Ordering::Relaxed) {
+                if !is_skip.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Some(window) = executor.get_weak().upgrade() else {
+                    return;
+                };
+                if should_stop(&window, &executor) {
+                    is_skip.store(false, Ordering::Relaxed);
+                    window.set_is_skip(false);
+                    return;
+                }
+
+                let mut executor = executor.clone();
+                let is_skip = is_skip.clone();
+                slint::spawn_local(async move {
+                    let name = executor.script_name();
+                    if let Err(e) = executor.execute_script() {
+                        executor.report_error("快进", e);
+                    }
+                    // 剧本切换即视为章节结束
+                    if let Some(window) = executor.get_weak().upgrade() {
+                        if window.get_stop_at_chapter() && executor.script_name() != name {
+                            is_skip.store(false, Ordering::Relaxed);
+                            window.set_is_skip(false);
+                        }
+                    }
+                })
+                .expect("skip-play timer: no slint event loop");
+            },
+        );
+
+        // 按住快进不受停止条件限制，只是在选择支和视频处空转
+        let executor = self.executor.clone();
+        let is_hold = self.is_hold.clone();
+        self.hold_timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_millis(30),
+            move || {
+                if is_hold.load(Ordering::Relaxed) {
                     let mut executor = executor.clone();
                     slint::spawn_local(async move {
                         if let Err(e) = executor.execute_script() {
-                            eprintln!("skip execute_script failed: {e}");
+                            executor.report_error("快进", e);
                         }
                     })
-                    .expect("skip-play timer: no slint event loop");
+                    .expect("fast-forward timer: no slint event loop");
                 }
             },
         );
     }
 }
+
+fn should_stop(window: &MainWindow, executor: &Executor) -> bool {
+    (window.get_stop_at_choice() && executor.is_choosing())
+        || (window.get_stop_at_video() && window.get_is_video())
+        || (!window.get_skip_all() && !executor.next_block_read())
+}
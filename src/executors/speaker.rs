@@ -0,0 +1,48 @@
+use crate::config::figure::figure_config;
+use crate::parser::markup::parse_color;
+use crate::ui::initialize::SpeakerStyle;
+use slint::{Image, SharedString};
+use std::collections::{BTreeMap, HashMap};
+
+/// 变量有值且不是 `0`、`false` 时视为已设置。
+fn is_set(variables: &BTreeMap<String, String>, flag: &str) -> bool {
+    variables
+        .get(flag)
+        .is_some_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+}
+
+/// 说话人实际显示的名字和样式，`faces` 是每个角色最近一次显示的表情。
+pub(crate) fn resolve(
+    speaker: &str,
+    variables: &BTreeMap<String, String>,
+    faces: &HashMap<String, Image>,
+) -> (SharedString, SpeakerStyle) {
+    let figure_config = figure_config();
+    let Some((character, display)) = figure_config
+        .character_of(speaker)
+        .and_then(|character| Some((character, figure_config.display(character)?)))
+    else {
+        return (speaker.into(), SpeakerStyle::default());
+    };
+
+    let name = match (&display.display_name, &display.reveal_flag) {
+        (Some(name), Some(flag)) if !is_set(variables, flag) => name.as_str(),
+        (Some(name), None) => name.as_str(),
+        _ => speaker,
+    };
+    let name_color = display.name_color.as_deref().and_then(parse_color);
+    let text_color = display.text_color.as_deref().and_then(parse_color);
+    let portrait = faces.get(character).filter(|_| display.portrait);
+    (
+        name.into(),
+        SpeakerStyle {
+            character: character.into(),
+            custom_name_color: name_color.is_some(),
+            name_color: name_color.unwrap_or_default(),
+            custom_text_color: text_color.is_some(),
+            text_color: text_color.unwrap_or_default(),
+            has_portrait: portrait.is_some(),
+            portrait: portrait.cloned().unwrap_or_default(),
+        },
+    )
+}
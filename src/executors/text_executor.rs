@@ -1,19 +1,26 @@
-use crate::ui::initialize::MainWindow;
-use slint::{ToSharedString, Weak};
-use std::sync::{mpsc::Receiver, Arc, RwLock};
+use crate::executors::text_layout;
+use crate::parser::markup::{self, Span, Tag};
+use crate::ui::initialize::{MainWindow, TextSpan};
+use slint::{ModelRc, VecModel, Weak};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    rc::Rc,
+    sync::{mpsc::Receiver, Arc, RwLock},
+};
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::time::{sleep, Duration};
 
 pub(crate) struct TextExecutor {
     timer: slint::Timer,
     weak: Weak<MainWindow>,
-    text_rx: Option<Receiver<String>>,
+    text_rx: Option<Receiver<Frame>>,
 }
 
 impl TextExecutor {
     pub(crate) fn new(weak: Weak<MainWindow>) -> (Self, Sender<Arc<RwLock<DisplayText>>>) {
         let (full_text_tx, mut full_text_rx) = channel::<Arc<RwLock<DisplayText>>>(10);
-        let (text_tx, text_rx) = std::sync::mpsc::channel::<String>();
+        let (text_tx, text_rx) = std::sync::mpsc::channel::<Frame>();
         let timer = slint::Timer::default();
 
         let executor = Self {
@@ -26,27 +33,29 @@ impl TextExecutor {
             while let Some(text) = full_text_rx.recv().await {
                 let speed = text.read().unwrap().speed;
                 if speed == Duration::from_millis(0) {
-                    let mut text = text.write().unwrap();
-                    let tx = text_tx.clone();
-                    if let Err(e) = tx.send(text.full_text.clone()) {
-                        eprintln!("text channel closed: {e}");
+                    let frame = text.write().unwrap().reveal();
+                    if let Err(e) = text_tx.send(frame) {
+                        log::error!("text channel closed: {e}");
                         return;
                     }
-                    text.is_running = false;
                     continue;
                 }
                 while text.read().unwrap().is_running {
                     let tx = text_tx.clone();
-                    {
+                    // {speed=...} 会改变之后的间隔，每个字重新读取
+                    let delay = {
                         let mut text = text.write().unwrap();
-                        if let Some(text) = text.next_character() {
-                            if let Err(e) = tx.send(text) {
-                                eprintln!("text channel closed: {e}");
+                        let mut delay = text.speed;
+                        if let Some((frame, pause)) = text.next_character() {
+                            if let Err(e) = tx.send(frame) {
+                                log::error!("text channel closed: {e}");
                                 return;
                             }
+                            delay = text.speed + pause;
                         }
-                    }
-                    sleep(speed).await;
+                        delay
+                    };
+                    sleep(delay).await;
                 }
             }
         });
@@ -62,17 +71,9 @@ impl TextExecutor {
             slint::TimerMode::Repeated,
             Duration::from_millis(20),
             move || {
-                if let Ok(text) = rx.try_recv() {
-                    let mut parts = text.split("{nns}").map(str::trim);
-                    let (t1, t2, t3) = (
-                        parts.next().unwrap_or(""),
-                        parts.next().unwrap_or(""),
-                        parts.next().unwrap_or(""),
-                    );
+                if let Ok(frame) = rx.try_recv() {
                     if let Some(window) = weak.upgrade() {
-                        window.set_dialogue_1(t1.to_shared_string());
-                        window.set_dialogue_2(t2.to_shared_string());
-                        window.set_dialogue_3(t3.to_shared_string());
+                        show_dialogue(&window, frame);
                     }
                 }
             },
@@ -80,12 +81,95 @@ impl TextExecutor {
     }
 }
 
+/// 打字机的一帧：台词全文和当前页已经显示到第几个字。
+pub(crate) struct Frame {
+    text: String,
+    start: usize,
+    shown: usize,
+}
+
+impl Frame {
+    /// 一次显示整句，用于选择支等没有打字动画的地方。
+    pub(crate) fn whole(text: String) -> Self {
+        Frame {
+            text,
+            start: 0,
+            shown: usize::MAX,
+        }
+    }
+}
+
+thread_local! {
+    // 窗口大小变化时按新的宽度重新排版
+    static LAST_FRAME: RefCell<Option<Frame>> = const { RefCell::new(None) };
+}
+
+impl From<Span> for TextSpan {
+    fn from(span: Span) -> Self {
+        TextSpan {
+            text: span.text.into(),
+            custom_color: span.color.is_some(),
+            color: span.color.unwrap_or_default(),
+            size: span.size,
+            bold: span.bold,
+            ruby: span.ruby.into(),
+        }
+    }
+}
+
+/// 排版后显示当前页已经出现的行。
+pub(crate) fn show_dialogue(window: &MainWindow, frame: Frame) {
+    let (lines, scale) = text_layout::layout(window, &frame.text);
+    let visible: Vec<_> = lines
+        .iter()
+        .filter(|line| {
+            line.start >= frame.start && (line.start < frame.shown || line.start == frame.start)
+        })
+        .collect();
+    // 窗口变窄后行数可能超出，保留最后几行
+    let skip = visible.len().saturating_sub(text_layout::max_lines(window));
+    window.set_dialogue_scale(scale);
+    window.set_dialogue_lines(lines_model(&visible[skip..], frame.shown));
+    LAST_FRAME.with(|last| *last.borrow_mut() = Some(frame));
+}
+
+/// 各行显示前 `shown` 个字（从整句开头算起）。
+pub(crate) fn lines_model(
+    lines: &[&text_layout::VisualLine],
+    shown: usize,
+) -> ModelRc<ModelRc<TextSpan>> {
+    let models: Vec<ModelRc<TextSpan>> = lines
+        .iter()
+        .map(|line| {
+            let spans: Vec<TextSpan> = line
+                .reveal(shown.saturating_sub(line.start))
+                .into_iter()
+                .map(TextSpan::from)
+                .collect();
+            Rc::new(VecModel::from(spans)).into()
+        })
+        .collect();
+    Rc::new(VecModel::from(models)).into()
+}
+
+/// 用最近一帧重新排版，窗口大小变化时调用。
+pub(crate) fn relayout(window: &MainWindow) {
+    if let Some(frame) = LAST_FRAME.with(|last| last.borrow_mut().take()) {
+        show_dialogue(window, frame);
+    }
+}
+
 pub(crate) type TextTX = Sender<Arc<RwLock<DisplayText>>>;
 
 pub(crate) struct DisplayText {
     pub(crate) full_text: String,
     pub(crate) speed: Duration,
     current_index: usize,
+    // 已经显示的字数
+    shown: usize,
+    page_start: usize,
+    // 之后各页开始的字序号
+    page_breaks: VecDeque<usize>,
     pub(crate) is_running: bool,
 }
 
@@ -95,40 +179,100 @@ impl DisplayText {
             full_text: String::new(),
             speed: Duration::default(),
             current_index: 0,
+            shown: 0,
+            page_start: 0,
+            page_breaks: VecDeque::new(),
             is_running: false,
         }
     }
 
-    pub(crate) fn start_animation(&mut self, text: String, speed: f32) {
+    fn reset(&mut self, text: String, page_breaks: Vec<usize>) {
         self.full_text = text;
-        self.speed = Duration::from_millis(speed as u64);
         self.current_index = 0;
+        self.shown = 0;
+        self.page_start = 0;
+        self.page_breaks = page_breaks.into();
         self.is_running = true;
     }
 
+    pub(crate) fn start_animation(&mut self, text: String, speed: f32, page_breaks: Vec<usize>) {
+        self.reset(text, page_breaks);
+        self.speed = Duration::from_millis(speed as u64);
+    }
+
+    /// 直接显示第一页，之后的页也不再有打字动画。
+    pub(crate) fn show_full(&mut self, text: String, page_breaks: Vec<usize>) {
+        self.reset(text, page_breaks);
+        self.speed = Duration::from_millis(0);
+    }
+
+    /// 当前页结束时的字数。
+    fn page_end(&self) -> usize {
+        let end = self.page_breaks.front().copied().unwrap_or(usize::MAX);
+        end.min(markup::char_count(&self.full_text))
+    }
+
+    /// 跳到当前页的最后一个字，下一帧显示整页后结束。
     pub(crate) fn end(&mut self) {
-        self.current_index = self.full_text.chars().count() - 1;
+        let end = self.page_end();
+        if end > self.shown {
+            self.shown = end - 1;
+            self.current_index = markup::skip_chars(&self.full_text, self.shown);
+        }
+    }
+
+    /// 当前页显示完后翻到下一页，没有下一页时返回 false。
+    pub(crate) fn next_page(&mut self) -> bool {
+        if self.is_running {
+            return false;
+        }
+        let Some(start) = self.page_breaks.pop_front() else {
+            return false;
+        };
+        self.page_start = start;
+        self.is_running = true;
+        true
+    }
+
+    fn frame(&self) -> Frame {
+        Frame {
+            text: self.full_text.clone(),
+            start: self.page_start,
+            shown: self.shown,
+        }
     }
 
-    fn next_character(&mut self) -> Option<String> {
+    /// 没有打字动画时一次显示整页。
+    fn reveal(&mut self) -> Frame {
+        self.shown = self.page_end();
+        self.current_index = markup::skip_chars(&self.full_text, self.shown);
+        self.is_running = false;
+        self.frame()
+    }
+
+    /// 下一帧显示的内容和这个字之后的额外停顿。
+    fn next_character(&mut self) -> Option<(Frame, Duration)> {
         if !self.is_running {
             return None;
         }
 
-        if self.current_index >= self.full_text.chars().count() {
+        if self.shown >= self.page_end() || self.current_index >= self.full_text.len() {
             self.is_running = false;
             return None;
         }
 
-        let chars: Vec<char> = self.full_text.chars().collect();
-        if self.current_index + 5 <= self.full_text.chars().count()
-            && chars[self.current_index..self.current_index + 5] == ['{', 'n', 'n', 's', '}']
-        {
-            self.current_index += 5;
+        let (end, tags) = markup::advance(&self.full_text, self.current_index);
+        let mut pause = Duration::default();
+        for tag in tags {
+            match tag {
+                Tag::Wait(millis) => pause += Duration::from_millis(millis),
+                Tag::Speed(millis) => self.speed = Duration::from_millis(millis),
+                _ => {}
+            }
         }
-        let displayed_text: String = chars[..=self.current_index].iter().collect();
-        self.current_index += 1;
+        self.current_index = end;
+        self.shown += 1;
 
-        Some(displayed_text)
+        Some((self.frame(), pause))
     }
 }
@@ -0,0 +1,276 @@
+use crate::config::{
+    font::{is_wide, SYSTEM_FONTS},
+    initialize::Overflow,
+    ENGINE_CONFIG,
+};
+use crate::executors::nvl::NVL_LINES;
+use crate::parser::markup::{self, Span};
+use crate::ui::initialize::MainWindow;
+use std::ops::Range;
+
+/// 不能出现在行首的字符（避头）。
+const NO_START: &str = "、。，．,.：；？！:;?!）)］]｝}〕〉》」』】〙〗〟’”｠»ゝゞーァィゥェォッャュョヮヵヶぁぃぅぇぉっゃゅょゎゕゖ々〻‐゠–〜～・…‥";
+/// 不能出现在行尾的字符（避尾）。
+const NO_END: &str = "（(［[｛{〔〈《「『【〘〖〝‘“｟«";
+/// shrink 模式下最小的缩放比例。
+const MIN_SCALE: f32 = 0.5;
+
+/// 对话框里的一行，`start` 是这一行第一个字在整句台词里的序号。
+pub(crate) struct VisualLine {
+    spans: Vec<Span>,
+    pub(crate) start: usize,
+}
+
+impl VisualLine {
+    /// 只显示前 `count` 个字，去掉行首行尾的空白。
+    pub(crate) fn reveal(&self, count: usize) -> Vec<Span> {
+        let mut left = count;
+        let mut spans = Vec::new();
+        for span in &self.spans {
+            if left == 0 {
+                break;
+            }
+            let text: String = span.text.chars().take(left).collect();
+            left -= text.chars().count();
+            spans.push(Span {
+                text,
+                ..span.clone()
+            });
+        }
+        if let Some(first) = spans.first_mut() {
+            first.text = first.text.trim_start().to_string();
+        }
+        if let Some(last) = spans.last_mut() {
+            last.text = last.text.trim_end().to_string();
+        }
+        spans.retain(|span| !span.text.is_empty());
+        spans
+    }
+}
+
+/// 排版用的台词区宽度和字体，和 story.slint 里绘制的一致。
+struct Metrics {
+    width: f32,
+    font_size: f32,
+    font: String,
+    bold: bool,
+}
+
+impl Metrics {
+    fn new(window: &MainWindow) -> Self {
+        // 台词区尺寸由 main_window.slint 计算，窗口显示前按 1280x720 估计。行宽留一点余量
+        let (width, font_size) = match (
+            window.get_dialogue_text_width(),
+            window.get_dialogue_font_size(),
+        ) {
+            (width, font_size) if width > 0.0 && font_size > 0.0 => (width, font_size),
+            _ => (1240.0, 24.0),
+        };
+        Metrics {
+            width: width * 0.98,
+            font_size,
+            font: window.get_dialogue_font().to_string(),
+            bold: window.get_is_bold(),
+        }
+    }
+}
+
+/// 一屏最多显示的行数和超出时的处理，NVL 模式下总是分页。
+fn overflow(window: &MainWindow) -> (usize, Overflow) {
+    if window.get_nvl_mode() {
+        (NVL_LINES, Overflow::Page)
+    } else {
+        (
+            ENGINE_CONFIG.dialogue_max_lines(),
+            ENGINE_CONFIG.dialogue_overflow(),
+        )
+    }
+}
+
+pub(crate) fn max_lines(window: &MainWindow) -> usize {
+    overflow(window).0
+}
+
+/// 排版后的台词和 shrink 模式下的缩放比例。
+pub(crate) fn layout(window: &MainWindow, text: &str) -> (Vec<VisualLine>, f32) {
+    let metrics = Metrics::new(window);
+    let (max_lines, overflow) = overflow(window);
+    let mut scale = 1.0;
+    loop {
+        let lines = wrap(text, &metrics, scale);
+        if overflow == Overflow::Page || lines.len() <= max_lines || scale <= MIN_SCALE {
+            return (lines, scale);
+        }
+        scale = (scale * 0.9).max(MIN_SCALE);
+    }
+}
+
+/// 分页模式下除第一页外每页开始的字序号，shrink 模式下为空。
+pub(crate) fn page_breaks(window: &MainWindow, text: &str) -> Vec<usize> {
+    let (max_lines, overflow) = overflow(window);
+    if overflow == Overflow::Shrink {
+        return Vec::new();
+    }
+    let (lines, _) = layout(window, text);
+    lines
+        .iter()
+        .skip(max_lines)
+        .step_by(max_lines)
+        .map(|line| line.start)
+        .collect()
+}
+
+fn wrap(text: &str, metrics: &Metrics, scale: f32) -> Vec<VisualLine> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for hard in markup::parse_lines(text) {
+        let atoms: Vec<(char, usize)> = hard
+            .iter()
+            .enumerate()
+            .flat_map(|(i, span)| span.text.chars().map(move |c| (c, i)))
+            .collect();
+        let widths = measure(&hard, metrics, metrics.font_size * scale);
+        for range in break_line(&hard, &atoms, &widths, metrics.width) {
+            lines.push(VisualLine {
+                spans: slice(&hard, &atoms[range.clone()]),
+                start: start + range.start,
+            });
+        }
+        start += atoms.len();
+    }
+    lines
+}
+
+/// 每个字的像素宽度。注音比正文宽时，多出来的宽度算在注音的最后一个字上。
+fn measure(hard: &[Span], metrics: &Metrics, font_size: f32) -> Vec<f32> {
+    let mut widths = Vec::new();
+    for span in hard {
+        let bold = metrics.bold || span.bold;
+        let size = font_size * span.size;
+        let chars: Vec<char> = span.text.chars().collect();
+        let mut span_widths: Vec<f32> = SYSTEM_FONTS
+            .measure(&metrics.font, bold, &chars)
+            .into_iter()
+            .map(|width| width * size)
+            .collect();
+        if !span.ruby.is_empty() {
+            let ruby: Vec<char> = span.ruby.chars().collect();
+            let ruby_width: f32 = SYSTEM_FONTS
+                .measure(&metrics.font, bold, &ruby)
+                .iter()
+                .sum::<f32>()
+                * size
+                * 0.45;
+            let extra = ruby_width - span_widths.iter().sum::<f32>();
+            if let (true, Some(last)) = (extra > 0.0, span_widths.last_mut()) {
+                *last += extra;
+            }
+        }
+        widths.extend(span_widths);
+    }
+    widths
+}
+
+/// 第 `i` 个字之前能否换行。
+fn can_break(hard: &[Span], atoms: &[(char, usize)], i: usize) -> bool {
+    let (prev, prev_span) = atoms[i - 1];
+    let (next, next_span) = atoms[i];
+    if prev_span == next_span && !hard[next_span].ruby.is_empty() {
+        return false;
+    }
+    if NO_START.contains(next) || NO_END.contains(prev) {
+        return false;
+    }
+    if prev.is_whitespace() {
+        return true;
+    }
+    !next.is_whitespace() && (is_wide(prev) || is_wide(next))
+}
+
+/// 贪心折行，空白可以超出行尾。找不到合适的断点时在超出的字前强制换行。
+fn break_line(
+    hard: &[Span],
+    atoms: &[(char, usize)],
+    widths: &[f32],
+    max_width: f32,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut width = 0.0;
+    let mut last_break = None;
+    for i in 0..atoms.len() {
+        if i > start && can_break(hard, atoms, i) {
+            last_break = Some(i);
+        }
+        width += widths[i];
+        if width <= max_width || atoms[i].0.is_whitespace() || i == start {
+            continue;
+        }
+        let at = last_break.unwrap_or(i);
+        ranges.push(start..at);
+        start = at;
+        width = widths[start..=i].iter().sum();
+        last_break = (start + 1..=i).rev().find(|j| can_break(hard, atoms, *j));
+    }
+    ranges.push(start..atoms.len());
+    ranges
+}
+
+fn slice(hard: &[Span], atoms: &[(char, usize)]) -> Vec<Span> {
+    let mut spans: Vec<(usize, Span)> = Vec::new();
+    for &(c, index) in atoms {
+        match spans.last_mut() {
+            Some((last, span)) if *last == index => span.text.push(c),
+            _ => spans.push((
+                index,
+                Span {
+                    text: c.to_string(),
+                    ..hard[index].clone()
+                },
+            )),
+        }
+    }
+    spans.into_iter().map(|(_, span)| span).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个字宽 1，按 `max_width` 个字折行。
+    fn lines(text: &str, max_width: f32) -> Vec<String> {
+        let hard = &markup::parse_lines(text)[0];
+        let atoms: Vec<(char, usize)> = hard
+            .iter()
+            .enumerate()
+            .flat_map(|(i, span)| span.text.chars().map(move |c| (c, i)))
+            .collect();
+        let widths = vec![1.0; atoms.len()];
+        break_line(hard, &atoms, &widths, max_width)
+            .into_iter()
+            .map(|range| atoms[range].iter().map(|(c, _)| c).collect())
+            .collect()
+    }
+
+    #[test]
+    fn no_start_chars_stay_on_the_previous_line() {
+        assert_eq!(lines("あいう。えお", 3.0), ["あい", "う。え", "お"]);
+        assert_eq!(lines("あいう、」え", 3.0), ["あい", "う、」", "え"]);
+    }
+
+    #[test]
+    fn no_end_chars_move_to_the_next_line() {
+        assert_eq!(lines("あい「うえ", 3.0), ["あい", "「うえ"]);
+    }
+
+    #[test]
+    fn ruby_is_not_split() {
+        assert_eq!(lines("あ{ruby=とうきょう}東京{/ruby}", 2.0), ["あ", "東京"]);
+    }
+
+    #[test]
+    fn latin_breaks_at_spaces() {
+        assert_eq!(lines("ab cd", 3.0), ["ab ", "cd"]);
+        assert_eq!(lines("abcd", 3.0), ["abc", "d"]);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::config::initialize::PreTitle;
+use std::collections::VecDeque;
+
+/// 标题前的播放队列，timer 持有当前图片的定时器。
+#[derive(Default)]
+pub(crate) struct Splash {
+    queue: VecDeque<PreTitle>,
+    active: bool,
+    timer: Option<slint::Timer>,
+}
+
+impl Splash {
+    pub(crate) fn start(&mut self, items: &[PreTitle]) {
+        self.queue = items.iter().cloned().collect();
+        self.active = true;
+        self.timer = None;
+    }
+
+    /// 取出下一项，队列空了即结束。
+    pub(crate) fn next(&mut self) -> Option<PreTitle> {
+        self.timer = None;
+        let item = self.queue.pop_front();
+        self.active = item.is_some();
+        item
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn set_timer(&mut self, timer: slint::Timer) {
+        self.timer = Some(timer);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::config::ENGINE_CONFIG;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    env,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 设置日志级别的环境变量，写法同 `[log] level`，如 `warn,media=debug`。
+const LOG_ENV: &str = "RUSTENG_LOG";
+const LOG_NAME: &str = "rusteng";
+
+static LOGGER: Logger = Logger {
+    state: Mutex::new(State {
+        filter: Filter {
+            default: LevelFilter::Info,
+            targets: Vec::new(),
+        },
+        file: None,
+    }),
+};
+
+struct Logger {
+    state: Mutex<State>,
+}
+
+struct State {
+    filter: Filter,
+    file: Option<RotatingFile>,
+}
+
+/// 默认级别加按目标（parser、executor、media、config、save）覆盖的级别。
+struct Filter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Filter {
+        let mut filter = Filter {
+            default: LevelFilter::Info,
+            targets: Vec::new(),
+        };
+        for item in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            let (target, level) = match item.split_once('=') {
+                Some((target, level)) => (Some(target.trim()), level.trim()),
+                None => (None, item),
+            };
+            let Ok(level) = level.parse::<LevelFilter>() else {
+                eprintln!("unknown log level `{level}` in `{spec}`");
+                continue;
+            };
+            match target {
+                Some(target) => filter.targets.push((target.to_string(), level)),
+                None => filter.default = level,
+            }
+        }
+        filter
+    }
+
+    fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(name, _)| name == target)
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+/// 按大小轮转的日志文件：rusteng.log 写满后依次改名为 rusteng.1.log、rusteng.2.log……
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    files: usize,
+}
+
+impl RotatingFile {
+    fn open(dir: PathBuf, max_size: u64, files: usize) -> io::Result<RotatingFile> {
+        fs::create_dir_all(&dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(format!("{LOG_NAME}.log")))?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            dir,
+            file,
+            size,
+            max_size,
+            files,
+        })
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(format!("{LOG_NAME}.log"))
+        } else {
+            self.dir.join(format!("{LOG_NAME}.{index}.log"))
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (0..self.files).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(from, self.path(index + 1))?;
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(self.path(0))?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// 按模块路径归类到日志目标，调用处显式写了 target 时以其为准。
+fn target<'a>(record: &'a Record) -> Option<&'a str> {
+    let module = record.module_path().unwrap_or_default();
+    let path = module.strip_prefix(LOG_NAME)?;
+    if record.target() != module {
+        return Some(record.target());
+    }
+    let path = path.strip_prefix("::").unwrap_or(path);
+    Some(if path.starts_with("parser") || path == "script" {
+        "parser"
+    } else if path.starts_with("executors") {
+        "executor"
+    } else if path.starts_with("media") {
+        "media"
+    } else if ["config::save_load", "config::extra", "config::transcript"]
+        .iter()
+        .any(|save| path.starts_with(save))
+    {
+        "save"
+    } else if path.starts_with("config") {
+        "config"
+    } else {
+        "engine"
+    })
+}
+
+/// UTC 时间，格式为 `YYYY-MM-DD hh:mm:ss.mmm`。
+pub(crate) fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, rest) = (secs / 86400, secs % 86400);
+
+    // 由天数推算公历日期
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{:03}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60,
+        now.subsec_millis()
+    )
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        // 第三方库只记录警告以上
+        let (target, level) = match target(record) {
+            Some(target) => (target, state.filter.level(target)),
+            None => ("deps", LevelFilter::Warn),
+        };
+        if record.level() > level {
+            return;
+        }
+
+        let line = format!(
+            "{} {:<5} [{target}] {}\n",
+            timestamp(),
+            record.level(),
+            record.args()
+        );
+        let _ = io::stderr().write_all(line.as_bytes());
+        if let Some(file) = state.file.as_mut() {
+            if let Err(e) = file.write(&line) {
+                eprintln!("failed to write log file: {e}");
+                state.file = None;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(file) = state.file.as_mut() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+fn env_filter() -> Option<Filter> {
+    env::var(LOG_ENV)
+        .ok()
+        .filter(|spec| !spec.trim().is_empty())
+        .map(|spec| Filter::parse(&spec))
+}
+
+fn set_filter(filter: Filter) {
+    log::set_max_level(filter.max_level());
+    if let Ok(mut state) = LOGGER.state.lock() {
+        state.filter = filter;
+    }
+}
+
+/// 尽早安装日志，配置加载前只输出到控制台。
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_err() {
+        return;
+    }
+    set_filter(env_filter().unwrap_or_else(|| Filter::parse("info")));
+}
+
+/// 配置加载后应用 `[log]` 设置，并开始写入存档目录下的日志文件。
+pub(crate) fn attach_file() {
+    set_filter(env_filter().unwrap_or_else(|| Filter::parse(ENGINE_CONFIG.log_level())));
+
+    let dir = PathBuf::from(ENGINE_CONFIG.save_path());
+    match RotatingFile::open(dir, ENGINE_CONFIG.log_max_size(), ENGINE_CONFIG.log_files()) {
+        Ok(file) => {
+            if let Ok(mut state) = LOGGER.state.lock() {
+                state.file = Some(file);
+            }
+        }
+        Err(e) => log::warn!("failed to open log file: {e}"),
+    }
+}
+
+/// 错误加上 source() 链。本 crate 的错误在 Display 里已经带上了内层错误，
+/// 已经出现在前文里的原因就不再重复。
+pub(crate) fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if !message.contains(&text) {
+            message.push_str(&format!("\n    caused by: {text}"));
+        }
+        source = cause.source();
+    }
+    message
+}
+
+/// 记录错误及其完整的 source() 链。
+pub(crate) fn log_error(context: &str, error: &dyn Error) {
+    let message = format!("{context}: {}", error_chain(error));
+    log::error!("{message}");
+    log::logger().flush();
+}
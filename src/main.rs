@@ -1,6 +1,9 @@
 mod config;
+#[cfg(feature = "control")]
+mod control;
 mod error;
 mod executors;
+mod logger;
 mod media;
 mod parser;
 mod script;
@@ -11,5 +14,6 @@ use crate::ui::initialize::ui;
 
 #[tokio::main]
 async fn main() -> Result<(), EngineError> {
-    ui().await
+    logger::init();
+    ui().await.inspect_err(|e| logger::log_error("rusteng", e))
 }
@@ -1,2 +1,3 @@
+pub(crate) mod placeholder;
 pub(crate) mod player;
 pub(crate) mod video_player;
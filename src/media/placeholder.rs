@@ -0,0 +1,127 @@
+use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
+use std::path::Path;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 180;
+const CELL: u32 = 20;
+const SCALE: u32 = 3;
+const MARGIN: u32 = 8;
+
+const MAGENTA: Rgba8Pixel = Rgba8Pixel::new(255, 0, 255, 255);
+const BLACK: Rgba8Pixel = Rgba8Pixel::new(0, 0, 0, 255);
+const WHITE: Rgba8Pixel = Rgba8Pixel::new(255, 255, 255, 255);
+
+/// 加载图片，文件缺失或无法解码时输出警告并返回占位图。
+pub(crate) fn load_image(path: &str) -> Image {
+    Image::load_from_path(Path::new(path)).unwrap_or_else(|e| {
+        log::warn!("failed to load image `{path}`: {e}");
+        let name = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        placeholder(&name)
+    })
+}
+
+/// 品红黑格底、白字写上资源名的占位图。
+pub(crate) fn placeholder(name: &str) -> Image {
+    let mut buffer = SharedPixelBuffer::<Rgba8Pixel>::new(WIDTH, HEIGHT);
+    let pixels = buffer.make_mut_slice();
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            pixels[(y * WIDTH + x) as usize] = if (x / CELL + y / CELL).is_multiple_of(2) {
+                MAGENTA
+            } else {
+                BLACK
+            };
+        }
+    }
+
+    // 3x5 点阵，每个字符占 4 列 6 行（含间隔）
+    let advance = 4 * SCALE;
+    let per_line = ((WIDTH - 2 * MARGIN) / advance) as usize;
+    let chars: Vec<char> = name.chars().collect();
+    let lines: Vec<&[char]> = chars.chunks(per_line.max(1)).collect();
+    let text_height = lines.len() as u32 * 6 * SCALE;
+    let top = HEIGHT.saturating_sub(text_height) / 2;
+
+    // 文字下方垫一条黑底，保证在格子上也能看清
+    for y in top.saturating_sub(MARGIN)..(top + text_height + MARGIN).min(HEIGHT) {
+        for x in 0..WIDTH {
+            pixels[(y * WIDTH + x) as usize] = BLACK;
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let line_width = line.len() as u32 * advance;
+        let left = WIDTH.saturating_sub(line_width) / 2;
+        let y0 = top + row as u32 * 6 * SCALE;
+        for (col, c) in line.iter().enumerate() {
+            let x0 = left + col as u32 * advance;
+            for (gy, bits) in glyph(*c).iter().enumerate() {
+                for (gx, bit) in bits.bytes().enumerate() {
+                    if bit != b'1' {
+                        continue;
+                    }
+                    for dy in 0..SCALE {
+                        for dx in 0..SCALE {
+                            let x = x0 + gx as u32 * SCALE + dx;
+                            let y = y0 + gy as u32 * SCALE + dy;
+                            if x < WIDTH && y < HEIGHT {
+                                pixels[(y * WIDTH + x) as usize] = WHITE;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Image::from_rgba8(buffer)
+}
+
+fn glyph(c: char) -> [&'static str; 5] {
+    match c.to_ascii_lowercase() {
+        '0' => ["111", "101", "101", "101", "111"],
+        '1' => ["010", "110", "010", "010", "111"],
+        '2' => ["111", "001", "111", "100", "111"],
+        '3' => ["111", "001", "111", "001", "111"],
+        '4' => ["101", "101", "111", "001", "001"],
+        '5' => ["111", "100", "111", "001", "111"],
+        '6' => ["111", "100", "111", "101", "111"],
+        '7' => ["111", "001", "001", "001", "001"],
+        '8' => ["111", "101", "111", "101", "111"],
+        '9' => ["111", "101", "111", "001", "111"],
+        'a' => ["010", "101", "111", "101", "101"],
+        'b' => ["110", "101", "110", "101", "110"],
+        'c' => ["011", "100", "100", "100", "011"],
+        'd' => ["110", "101", "101", "101", "110"],
+        'e' => ["111", "100", "110", "100", "111"],
+        'f' => ["111", "100", "110", "100", "100"],
+        'g' => ["011", "100", "101", "101", "011"],
+        'h' => ["101", "101", "111", "101", "101"],
+        'i' => ["111", "010", "010", "010", "111"],
+        'j' => ["001", "001", "001", "101", "010"],
+        'k' => ["101", "101", "110", "101", "101"],
+        'l' => ["100", "100", "100", "100", "111"],
+        'm' => ["101", "111", "111", "101", "101"],
+        'n' => ["110", "101", "101", "101", "101"],
+        'o' => ["010", "101", "101", "101", "010"],
+        'p' => ["110", "101", "110", "100", "100"],
+        'q' => ["010", "101", "101", "011", "001"],
+        'r' => ["110", "101", "110", "101", "101"],
+        's' => ["011", "100", "010", "001", "110"],
+        't' => ["111", "010", "010", "010", "010"],
+        'u' => ["101", "101", "101", "101", "111"],
+        'v' => ["101", "101", "101", "101", "010"],
+        'w' => ["101", "101", "111", "111", "101"],
+        'x' => ["101", "101", "010", "101", "101"],
+        'y' => ["101", "101", "010", "010", "010"],
+        'z' => ["111", "001", "010", "100", "111"],
+        '_' => ["000", "000", "000", "000", "111"],
+        '-' => ["000", "000", "111", "000", "000"],
+        '.' => ["000", "000", "000", "000", "010"],
+        ' ' => ["000", "000", "000", "000", "000"],
+        _ => ["111", "001", "010", "000", "010"],
+    }
+}
@@ -1,6 +1,29 @@
 use crate::error::MediaError;
 use rodio::{Decoder, OutputStream, Sink, Source};
-use std::{cell::RefCell, fs::File, io::BufReader};
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::BufReader,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// 计算一次音量的窗口长度。
+const LEVEL_WINDOW: Duration = Duration::from_millis(40);
+
+/// 音频文件的时长。解码器给不出时长时（如 ogg）数一遍采样。
+pub(crate) fn audio_duration(path: &str) -> Option<Duration> {
+    let decoder = Decoder::new(BufReader::new(File::open(path).ok()?)).ok()?;
+    if let Some(duration) = decoder.total_duration() {
+        return Some(duration);
+    }
+    let rate = decoder.sample_rate() as f64 * decoder.channels() as f64;
+    let samples = decoder.count();
+    (rate > 0.0).then(|| Duration::from_secs_f64(samples as f64 / rate))
+}
 
 pub(crate) struct MediaPlayer {
     bgm_player: Player,
@@ -33,6 +56,11 @@ impl MediaPlayer {
         self.voice_player.play_voice(path, volume)
     }
 
+    /// 正在播放的配音最近一个窗口的 RMS 音量（0~1），没有在播放时为 None。
+    pub(crate) fn voice_level(&self) -> Option<f32> {
+        self.voice_player.level()
+    }
+
     pub(crate) fn stop_bgm(&self) {
         self.bgm_player.stop();
     }
@@ -45,6 +73,7 @@ impl MediaPlayer {
 
 pub(crate) struct Player {
     sink: RefCell<Option<Sink>>,
+    level: RefCell<Option<Arc<AtomicU32>>>,
     _stream: OutputStream,
     stream_handle: rodio::OutputStreamHandle,
 }
@@ -62,6 +91,7 @@ impl Player {
         let (_stream, handle) = OutputStream::try_default()?;
         Ok(Self {
             sink: RefCell::new(None),
+            level: RefCell::new(None),
             _stream,
             stream_handle: handle,
         })
@@ -117,13 +147,87 @@ impl Player {
             path: path.to_string(),
             source: e,
         })?;
+        let level = Arc::new(AtomicU32::new(0));
 
         let sink = Sink::try_new(&self.stream_handle)?;
-        sink.append(source);
+        sink.append(Meter::new(source, level.clone()));
         sink.set_volume(volume);
         sink.play();
 
         *self.sink.borrow_mut() = Some(sink);
+        *self.level.borrow_mut() = Some(level);
         Ok(())
     }
+
+    fn level(&self) -> Option<f32> {
+        let sink = self.sink.borrow();
+        let level = self.level.borrow();
+        match (sink.as_ref(), level.as_ref()) {
+            (Some(sink), Some(level)) if !sink.empty() => {
+                Some(f32::from_bits(level.load(Ordering::Relaxed)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 原样输出采样，同时按窗口计算 RMS 音量，口型同步用。
+struct Meter<S> {
+    inner: S,
+    level: Arc<AtomicU32>,
+    window: usize,
+    count: usize,
+    sum: f32,
+}
+
+impl<S: Source<Item = i16>> Meter<S> {
+    fn new(inner: S, level: Arc<AtomicU32>) -> Self {
+        let samples = inner.sample_rate() as f32 * inner.channels() as f32;
+        Meter {
+            window: ((samples * LEVEL_WINDOW.as_secs_f32()) as usize).max(1),
+            inner,
+            level,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for Meter<S> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let Some(sample) = self.inner.next() else {
+            self.level.store(0, Ordering::Relaxed);
+            return None;
+        };
+        let value = sample as f32 / i16::MAX as f32;
+        self.sum += value * value;
+        self.count += 1;
+        if self.count == self.window {
+            let rms = (self.sum / self.count as f32).sqrt();
+            self.level.store(rms.to_bits(), Ordering::Relaxed);
+            self.count = 0;
+            self.sum = 0.0;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = i16>> Source for Meter<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
 }
@@ -29,7 +29,7 @@ static FFMPEG_INIT: Once = Once::new();
 fn ensure_ffmpeg_initialized() {
     FFMPEG_INIT.call_once(|| {
         if let Err(e) = ffmpeg::init() {
-            eprintln!("ffmpeg init failed: {e}");
+            log::error!("ffmpeg init failed: {e}");
         }
         ffmpeg::util::log::set_level(ffmpeg::util::log::Level::Error);
     });
@@ -107,7 +107,7 @@ impl VideoPlayer {
                     finished_thread.clone(),
                     latest_frame_thread,
                 ) {
-                    eprintln!("video decode failed: {e}");
+                    log::error!("video decode failed: {e}");
                 }
                 finished_thread.store(true, Ordering::Release);
             })
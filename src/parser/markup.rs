@@ -0,0 +1,387 @@
+use slint::Color;
+
+/// 台词里的行内标记，写法如 `{color=#f66}红字{/color}`。
+/// 不认识的 `{...}` 按普通文字显示。
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Tag {
+    LineBreak,
+    Color(Color),
+    ColorEnd,
+    /// 相对于设置字号的倍数
+    Size(f32),
+    SizeEnd,
+    Bold,
+    BoldEnd,
+    Ruby(String),
+    RubyEnd,
+    /// 打字机停顿的毫秒数
+    Wait(u64),
+    /// 之后每个字的间隔毫秒数
+    Speed(u64),
+}
+
+/// `#rgb`、`#rrggbb` 或 `#rrggbbaa`。
+pub(crate) fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    let (r, g, b, a) = match digits.as_slice() {
+        [r, g, b] => (r * 17, g * 17, b * 17, 255),
+        [r1, r2, g1, g2, b1, b2] => (r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2, 255),
+        [r1, r2, g1, g2, b1, b2, a1, a2] => {
+            (r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2, a1 * 16 + a2)
+        }
+        _ => return None,
+    };
+    Some(Color::from_argb_u8(a, r, g, b))
+}
+
+/// 文本开头的标记和它的字节长度，不是合法标记时返回 None。
+fn tag_at(text: &str) -> Option<(Tag, usize)> {
+    let rest = text.strip_prefix('{')?;
+    let end = rest.find('}')?;
+    let body = &rest[..end];
+    let tag = match body.split_once('=') {
+        Some(("color", value)) => Tag::Color(parse_color(value)?),
+        Some(("size", value)) => Tag::Size(value.parse().ok().filter(|size: &f32| *size > 0.0)?),
+        Some(("ruby", value)) => Tag::Ruby(value.to_string()),
+        Some(("w", value)) => Tag::Wait(value.parse().ok()?),
+        Some(("speed", value)) => Tag::Speed(value.parse().ok()?),
+        Some(_) => return None,
+        None => match body {
+            "nns" => Tag::LineBreak,
+            "/color" => Tag::ColorEnd,
+            "/size" => Tag::SizeEnd,
+            "b" => Tag::Bold,
+            "/b" => Tag::BoldEnd,
+            "/ruby" => Tag::RubyEnd,
+            _ => return None,
+        },
+    };
+    Some((tag, end + 2))
+}
+
+pub(crate) enum Piece {
+    Char(char),
+    Tag(Tag),
+}
+
+/// 把台词拆成文字和标记，同时给出每一段结束的字节位置。
+pub(crate) struct Pieces<'a> {
+    text: &'a str,
+    index: usize,
+}
+
+impl<'a> Pieces<'a> {
+    pub(crate) fn new(text: &'a str, index: usize) -> Self {
+        Pieces { text, index }
+    }
+}
+
+impl Iterator for Pieces<'_> {
+    type Item = (Piece, usize);
+
+    fn next(&mut self) -> Option<(Piece, usize)> {
+        let rest = self.text.get(self.index..)?;
+        if let Some((tag, len)) = tag_at(rest) {
+            self.index += len;
+            return Some((Piece::Tag(tag), self.index));
+        }
+        let c = rest.chars().next()?;
+        self.index += c.len_utf8();
+        Some((Piece::Char(c), self.index))
+    }
+}
+
+/// 一段样式相同的文字。
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Span {
+    pub(crate) text: String,
+    pub(crate) color: Option<Color>,
+    pub(crate) size: f32,
+    pub(crate) bold: bool,
+    pub(crate) ruby: String,
+}
+
+#[derive(Default)]
+struct Style {
+    colors: Vec<Color>,
+    sizes: Vec<f32>,
+    bold: usize,
+    ruby: Option<String>,
+    // 每个 {ruby=...} 单独成段，不和相邻的同注音文字合并
+    ruby_group: usize,
+}
+
+impl Style {
+    fn apply(&mut self, tag: Tag) {
+        match tag {
+            Tag::Color(color) => self.colors.push(color),
+            Tag::ColorEnd => {
+                self.colors.pop();
+            }
+            Tag::Size(size) => self.sizes.push(size),
+            Tag::SizeEnd => {
+                self.sizes.pop();
+            }
+            Tag::Bold => self.bold += 1,
+            Tag::BoldEnd => self.bold = self.bold.saturating_sub(1),
+            Tag::Ruby(ruby) => {
+                self.ruby = Some(ruby);
+                self.ruby_group += 1;
+            }
+            Tag::RubyEnd => self.ruby = None,
+            Tag::LineBreak | Tag::Wait(_) | Tag::Speed(_) => {}
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            text: String::new(),
+            color: self.colors.last().copied(),
+            size: self.sizes.last().copied().unwrap_or(1.0),
+            bold: self.bold > 0,
+            ruby: self.ruby.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// 按 `{nns}` 分行并拆成样式段，没闭合的标记一直作用到文本末尾。
+pub(crate) fn parse_lines(text: &str) -> Vec<Vec<Span>> {
+    let mut lines: Vec<Vec<(usize, Span)>> = vec![Vec::new()];
+    let mut style = Style::default();
+    for (piece, _) in Pieces::new(text, 0) {
+        let line = lines.last_mut().expect("lines is never empty");
+        match piece {
+            Piece::Char(c) => {
+                let group = if style.ruby.is_some() {
+                    style.ruby_group
+                } else {
+                    0
+                };
+                let span = style.span();
+                match line.last_mut() {
+                    Some((last_group, last))
+                        if *last_group == group
+                            && (last.color, last.size, last.bold, &last.ruby)
+                                == (span.color, span.size, span.bold, &span.ruby) =>
+                    {
+                        last.text.push(c)
+                    }
+                    _ => line.push((
+                        group,
+                        Span {
+                            text: c.to_string(),
+                            ..span
+                        },
+                    )),
+                }
+            }
+            Piece::Tag(Tag::LineBreak) => lines.push(Vec::new()),
+            Piece::Tag(tag) => style.apply(tag),
+        }
+    }
+
+    // 不去掉首尾空白，保证字数和打字机一致；显示时再去掉
+    lines
+        .into_iter()
+        .map(|line| line.into_iter().map(|(_, span)| span).collect())
+        .collect()
+}
+
+/// 去掉标记后的纯文本，`{nns}` 换成换行，注音只保留正文。用于履历和存档预览。
+pub(crate) fn strip(text: &str) -> String {
+    Pieces::new(text, 0)
+        .filter_map(|(piece, _)| match piece {
+            Piece::Char(c) => Some(c),
+            Piece::Tag(Tag::LineBreak) => Some('\n'),
+            Piece::Tag(_) => None,
+        })
+        .collect()
+}
+
+/// 打字机前进一个字：跳过前后的标记，返回这个字之后的位置和经过的标记。
+pub(crate) fn advance(text: &str, index: usize) -> (usize, Vec<Tag>) {
+    let mut tags = Vec::new();
+    let mut end = index;
+    let mut shown = false;
+    for (piece, next) in Pieces::new(text, index) {
+        match piece {
+            Piece::Char(_) if shown => break,
+            Piece::Char(_) => shown = true,
+            Piece::Tag(tag) => tags.push(tag),
+        }
+        end = next;
+    }
+    (end, tags)
+}
+
+/// 显示前 `count` 个字之后打字机所在的位置。
+pub(crate) fn skip_chars(text: &str, count: usize) -> usize {
+    let mut index = 0;
+    for _ in 0..count {
+        if index >= text.len() {
+            break;
+        }
+        index = advance(text, index).0;
+    }
+    index
+}
+
+/// 去掉标记后的字数，不含 `{nns}`。
+pub(crate) fn char_count(text: &str) -> usize {
+    Pieces::new(text, 0)
+        .filter(|(piece, _)| matches!(piece, Piece::Char(_)))
+        .count()
+}
+
+/// 检查台词里没闭合或多余的结束标记，供解析剧本时提示。
+pub(crate) fn check(text: &str) -> Vec<String> {
+    let mut open: Vec<&str> = Vec::new();
+    let mut problems = Vec::new();
+    for (piece, _) in Pieces::new(text, 0) {
+        let Piece::Tag(tag) = piece else {
+            continue;
+        };
+        let (name, closing) = match tag {
+            Tag::Color(_) => ("color", false),
+            Tag::ColorEnd => ("color", true),
+            Tag::Size(_) => ("size", false),
+            Tag::SizeEnd => ("size", true),
+            Tag::Bold => ("b", false),
+            Tag::BoldEnd => ("b", true),
+            Tag::Ruby(_) => ("ruby", false),
+            Tag::RubyEnd => ("ruby", true),
+            Tag::LineBreak | Tag::Wait(_) | Tag::Speed(_) => continue,
+        };
+        if !closing {
+            open.push(name);
+        } else if let Some(position) = open.iter().rposition(|tag| *tag == name) {
+            open.remove(position);
+        } else {
+            problems.push(format!("`{{/{name}}}` has no matching `{{{name}}}`"));
+        }
+    }
+    problems.extend(
+        open.iter()
+            .map(|name| format!("`{{{name}}}` is not closed")),
+    );
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red() -> Color {
+        Color::from_rgb_u8(255, 0, 0)
+    }
+
+    fn texts(line: &[Span]) -> Vec<&str> {
+        line.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn tag_at_reads_known_tags() {
+        assert_eq!(tag_at("{color=#f00}a"), Some((Tag::Color(red()), 12)));
+        assert_eq!(tag_at("{size=1.5}"), Some((Tag::Size(1.5), 10)));
+        assert_eq!(tag_at("{b}"), Some((Tag::Bold, 3)));
+        assert_eq!(tag_at("{/ruby}"), Some((Tag::RubyEnd, 7)));
+        assert_eq!(tag_at("{w=300}"), Some((Tag::Wait(300), 7)));
+        assert_eq!(tag_at("{speed=20}"), Some((Tag::Speed(20), 10)));
+        assert_eq!(tag_at("{nns}"), Some((Tag::LineBreak, 5)));
+    }
+
+    #[test]
+    fn tag_at_rejects_invalid_tags() {
+        for text in [
+            "a{b}",
+            "{b",
+            "{foo}",
+            "{foo=1}",
+            "{color=red}",
+            "{color=#ff}",
+            "{size=0}",
+            "{w=}",
+            "{speed=fast}",
+        ] {
+            assert_eq!(tag_at(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn parse_lines_nests_styles() {
+        let lines = parse_lines("{color=#f00}a{b}b{/b}c{/color}d");
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(texts(line), ["a", "b", "c", "d"]);
+        assert_eq!(line[0].color, Some(red()));
+        assert!(line[1].bold && line[1].color == Some(red()));
+        assert!(!line[2].bold && line[2].color == Some(red()));
+        assert_eq!(line[3].color, None);
+    }
+
+    #[test]
+    fn parse_lines_keeps_unclosed_tags_to_the_end() {
+        let lines = parse_lines("{size=2}a{nns}b");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][0].size, 2.0);
+        assert_eq!(lines[1][0].size, 2.0);
+    }
+
+    #[test]
+    fn parse_lines_shows_unknown_tags_as_text() {
+        let lines = parse_lines("{foo}{color=red}a");
+        assert_eq!(texts(&lines[0]), ["{foo}{color=red}a"]);
+    }
+
+    #[test]
+    fn parse_lines_keeps_each_ruby_as_one_span() {
+        let lines = parse_lines("{ruby=とうきょう}東京{/ruby}{ruby=えき}駅{/ruby}へ");
+        let line = &lines[0];
+        assert_eq!(texts(line), ["東京", "駅", "へ"]);
+        assert_eq!(line[0].ruby, "とうきょう");
+        assert_eq!(line[1].ruby, "えき");
+        assert_eq!(line[2].ruby, "");
+    }
+
+    #[test]
+    fn strip_keeps_plain_text() {
+        assert_eq!(
+            strip("{color=#f00}赤{/color}{nns}{ruby=かん}漢{/ruby}{w=500}{foo}"),
+            "赤\n漢{foo}"
+        );
+        assert_eq!(char_count("{b}ab{/b}{nns}c"), 3);
+    }
+
+    #[test]
+    fn advance_collects_pauses() {
+        let text = "{speed=50}a{w=300}b";
+        assert_eq!(advance(text, 0), (18, vec![Tag::Speed(50), Tag::Wait(300)]));
+        assert_eq!(advance(text, 18), (19, vec![]));
+        assert_eq!(advance(text, 19), (19, vec![]));
+    }
+
+    #[test]
+    fn skip_chars_stops_after_tags() {
+        let text = "{b}ab{/b}c";
+        assert_eq!(skip_chars(text, 0), 0);
+        assert_eq!(skip_chars(text, 1), 4);
+        assert_eq!(skip_chars(text, 2), 9);
+        assert_eq!(skip_chars(text, 10), text.len());
+    }
+
+    #[test]
+    fn check_reports_unbalanced_tags() {
+        assert!(check("{color=#f00}{b}a{/b}{/color}").is_empty());
+        assert_eq!(
+            check("{b}a{/color}"),
+            [
+                "`{/color}` has no matching `{color}`",
+                "`{b}` is not closed"
+            ]
+        );
+    }
+}
@@ -1 +1,2 @@
+pub(crate) mod markup;
 pub(crate) mod script_parser;
@@ -1,7 +1,10 @@
-use crate::config::ENGINE_CONFIG;
+use crate::config::{
+    textbox::{DEFAULT_STYLE, TEXTBOX_THEME},
+    ENGINE_CONFIG,
+};
 use crate::error::{EngineError, ScriptError};
-use crate::script::{Label, Script};
-use std::collections::HashMap;
+use crate::parser::markup;
+use crate::script::{Label, Script, TextboxState};
 use std::fs;
 
 #[derive(Debug, Clone)]
@@ -29,6 +32,8 @@ pub(crate) enum Command {
     Dialogue {
         speaker: String,
         text: String,
+        // 自动配音用的台词 ID
+        line_id: String,
     },
     Figure {
         name: String,
@@ -47,9 +52,32 @@ pub(crate) enum Command {
         delay: Option<String>,
     },
     Clear(String),
-    Choice((String, HashMap<String, Label>)),
+    /// 选择支按剧本里的顺序排列，回放时按序号选择。
+    Choice((String, Vec<(String, Label)>)),
     Jump(Label),
     Label,
+    Nvl(NvlAction),
+    Textbox(TextboxAction),
+    Set {
+        name: String,
+        value: String,
+    },
+}
+
+/// `@textbox` 的参数：隐藏、显示对话框，或者换成主题里的样式。
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TextboxAction {
+    Hide,
+    Show,
+    Style(String),
+}
+
+/// `@nvl` 的参数：进入、退出全屏文字模式，或者换一页。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NvlAction {
+    On,
+    Off,
+    Clear,
 }
 
 impl Command {
@@ -97,9 +125,27 @@ impl Command {
 
 static VERSION: usize = 1;
 
+/// 块内容的 FNV-1a 哈希，注释行不参与计算。
+/// 存档会持久化这个值，所以不能用随编译器版本变化的 `DefaultHasher`。
+fn block_hash(lines: &[(usize, String)]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (_, line) in lines.iter().filter(|(_, line)| !line.starts_with('#')) {
+        for byte in line.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
 pub(crate) struct Parser {
     script: Script,
     block_index: usize,
+    // 按剧本顺序累积的对话框状态，记进时间线供读档恢复
+    textbox: TextboxState,
+    // 最近的标签和其后第几句台词，用来生成台词 ID
+    label: Option<String>,
+    line_ordinal: usize,
 }
 
 impl Parser {
@@ -109,6 +155,9 @@ impl Parser {
         Parser {
             script,
             block_index: 0,
+            textbox: TextboxState::default(),
+            label: None,
+            line_ordinal: 0,
         }
     }
 
@@ -171,7 +220,7 @@ impl Parser {
                         }
                         "choose" => {
                             let num = arg.parse::<usize>().map_err(ScriptError::from)?;
-                            let mut choose_branch = HashMap::with_capacity(num);
+                            let mut choose_branch = Vec::with_capacity(num);
                             let explain = lines[index + 1].1.clone();
                             for (i, line) in lines.iter().take(index + num + 1 + 1).skip(index + 2)
                             {
@@ -199,7 +248,8 @@ impl Parser {
                                         ),
                                         _ => unreachable!(),
                                     };
-                                    choose_branch.insert(choice.clone(), label.clone());
+                                    choose_branch.retain(|(text, _)| *text != choice);
+                                    choose_branch.push((choice.clone(), label.clone()));
                                     self.script.insert_choice(choice, label);
                                 } else {
                                     return Err(EngineError::from(ScriptError::Choice(format!(
@@ -356,8 +406,81 @@ impl Parser {
                         },
                         "label" => {
                             self.script.insert_label(arg.to_string(), self.block_index);
+                            self.label = Some(arg.to_string());
+                            self.line_ordinal = 0;
                             Label
                         }
+                        "textbox" => {
+                            let action = match arg.trim() {
+                                "hide" => TextboxAction::Hide,
+                                "show" => TextboxAction::Show,
+                                arg => match arg.strip_prefix("style=").map(str::trim) {
+                                    Some(style) if !style.is_empty() => {
+                                        if !TEXTBOX_THEME.contains(style) {
+                                            log::warn!(
+                                                "line {line_num}: textbox style `{style}` is not defined in textbox.toml"
+                                            );
+                                        }
+                                        TextboxAction::Style(style.to_string())
+                                    }
+                                    _ => {
+                                        return Err(EngineError::from(
+                                            ScriptError::InvalidCommand {
+                                                line: *line_num,
+                                                content: line.to_string(),
+                                            },
+                                        ));
+                                    }
+                                },
+                            };
+                            match &action {
+                                TextboxAction::Hide => self.textbox.hidden = true,
+                                TextboxAction::Show => self.textbox.hidden = false,
+                                TextboxAction::Style(style) if style == DEFAULT_STYLE => {
+                                    self.textbox.style.clear()
+                                }
+                                TextboxAction::Style(style) => self.textbox.style = style.clone(),
+                            }
+                            self.script
+                                .insert_textbox(self.block_index, self.textbox.clone());
+                            Textbox(action)
+                        }
+                        // 省略值时设为 1
+                        "set" => match arg.split_once('|') {
+                            _ if arg.trim().is_empty() => {
+                                return Err(EngineError::from(ScriptError::ArgsTooShort {
+                                    cmd: "set".to_string(),
+                                    line: *line_num,
+                                    content: line.to_string(),
+                                }))
+                            }
+                            Some((name, value)) => Set {
+                                name: name.trim().to_string(),
+                                value: value.trim().to_string(),
+                            },
+                            None => Set {
+                                name: arg.trim().to_string(),
+                                value: "1".to_string(),
+                            },
+                        },
+                        "nvl" => {
+                            let nvl = match arg.trim() {
+                                "on" => NvlAction::On,
+                                "off" => NvlAction::Off,
+                                "clear" => NvlAction::Clear,
+                                _ => {
+                                    return Err(EngineError::from(ScriptError::InvalidCommand {
+                                        line: *line_num,
+                                        content: line.to_string(),
+                                    }));
+                                }
+                            };
+                            if nvl != NvlAction::Clear {
+                                self.script
+                                    .insert_nvl(self.block_index, nvl == NvlAction::On);
+                            }
+                            Command::Nvl(nvl)
+                        }
                         _ => {
                             return Err(EngineError::from(ScriptError::InvalidCommand {
                                 line: *line_num,
@@ -396,10 +519,26 @@ impl Parser {
             } else if line.strip_prefix('#').is_some() {
                 continue;
             } else if let Some((speaker, text)) = line.split_once("“") {
-                if let Some(text) = text.strip_suffix("”") {
+                // 台词后可以用 `#id` 指定台词 ID
+                let split = text
+                    .rsplit_once("”")
+                    .filter(|(_, id)| id.is_empty() || id.starts_with('#'));
+                if let Some((text, id)) = split {
+                    for problem in markup::check(text) {
+                        log::warn!("line {line_num}: {problem} in `{text}`");
+                    }
+                    self.line_ordinal += 1;
+                    let line_id = match (id.strip_prefix('#').map(str::trim), &self.label) {
+                        (Some(id), _) if !id.is_empty() => id.to_string(),
+                        (_, Some(label)) => {
+                            format!("{}_{}_{}", self.script.name(), label, self.line_ordinal)
+                        }
+                        (_, None) => format!("{}_{}", self.script.name(), self.line_ordinal),
+                    };
                     block_commands.push(Dialogue {
                         speaker: speaker.trim().to_string(),
                         text: text.trim().to_string(),
+                        line_id,
                     });
                     break;
                 } else {
@@ -418,11 +557,14 @@ impl Parser {
 
         if block_commands.len() == 1 {
             self.block_index += 1;
-            self.script
-                .push_command(OneCmd(block_commands.into_iter().next().unwrap()));
+            self.script.push_command(
+                OneCmd(block_commands.into_iter().next().unwrap()),
+                block_hash(lines),
+            );
         } else if block_commands.len() > 1 {
             self.block_index += 1;
-            self.script.push_command(VarCmds(block_commands))
+            self.script
+                .push_command(VarCmds(block_commands), block_hash(lines))
         }
 
         Ok(())
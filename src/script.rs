@@ -7,11 +7,35 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 
 pub(crate) type Label = (String, String);
 
+/// 履历中选择支及所选项使用的说话人名。
+pub(crate) const CHOICE_SPEAKER: &str = "选择支";
+pub(crate) const CHOSEN_SPEAKER: &str = "已选择";
+/// 存档预览最多保留的字节数，约 6 个汉字或 18 个拉丁字母。
+const EXPLAIN_MAX_BYTES: usize = 18;
+
+/// 存档锚点：最近的前置标签 + 相对偏移，以及该块内容的哈希。
+/// 剧本被修改后用它重新定位存档位置，而不是直接依赖块号。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Anchor {
+    pub(crate) label: String,
+    pub(crate) offset: usize,
+    pub(crate) hash: String,
+}
+
 #[derive(Debug, Clone, Default)]
 pub(crate) struct PreItems {
-    pre_bg: Option<Command>,
-    pre_bgm: PreBgm,
-    pre_figures: Option<Figure>,
+    pub(crate) pre_bg: Option<Command>,
+    pub(crate) pre_bgm: PreBgm,
+    pub(crate) pre_figures: Option<Figure>,
+    pub(crate) pre_nvl: Option<bool>,
+    pub(crate) pre_textbox: Option<TextboxState>,
+}
+
+/// 剧本控制的对话框状态：是否隐藏和主题样式名，样式为空表示默认样式。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TextboxState {
+    pub(crate) hidden: bool,
+    pub(crate) style: String,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -19,6 +43,8 @@ pub(crate) struct Timeline {
     bgm: BTreeMap<usize, String>,
     backgrounds: BTreeMap<usize, Command>,
     figures: BTreeMap<usize, Figure>,
+    nvl: BTreeMap<usize, bool>,
+    textbox: BTreeMap<usize, TextboxState>,
 }
 
 impl Timeline {
@@ -30,6 +56,14 @@ impl Timeline {
         self.backgrounds.insert(index, command);
     }
 
+    fn insert_nvl(&mut self, index: usize, on: bool) {
+        self.nvl.insert(index, on);
+    }
+
+    fn insert_textbox(&mut self, index: usize, state: TextboxState) {
+        self.textbox.insert(index, state);
+    }
+
     fn update_figures(&mut self, index: usize, distance: &str, position: &str, command: Command) {
         self.figures
             .entry(index)
@@ -73,15 +107,59 @@ impl Timeline {
             .range(..=index)
             .next_back()
             .map(|(_, fg)| fg.clone());
+        // 目标位置之前没有 @nvl 时保持当前模式，跨剧本跳转不会退出 NVL；读档和新游戏由 load_jump 重置
+        let pre_nvl = self.nvl.range(..=index).next_back().map(|(_, on)| *on);
+        // 同上，没有 @textbox 时保持当前的显示状态和样式
+        let pre_textbox = self
+            .textbox
+            .range(..=index)
+            .next_back()
+            .map(|(_, state)| state.clone());
         PreItems {
             pre_bg,
             pre_bgm,
             pre_figures,
+            pre_nvl,
+            pre_textbox,
         }
     }
 }
 
-const WINDOW_SIZE: usize = 4;
+/// 履历界面的检索条件。
+#[derive(Debug, Clone, Default)]
+struct BacklogFilter {
+    query: String,
+    // (角色名, 全名)，剧本里的说话人通常是全名的一部分
+    speaker: Option<(String, String)>,
+}
+
+impl BacklogFilter {
+    fn matches(&self, item: &BackLogItem) -> bool {
+        let speaker = self.speaker.as_ref().is_none_or(|(name, full_name)| {
+            item.chara == name.as_str()
+                || (!item.front.is_empty() && full_name.contains(item.front.as_str()))
+        });
+        speaker
+            && (self.query.is_empty()
+                || item.back.contains(self.query.as_str())
+                || item.front.contains(self.query.as_str()))
+    }
+
+    /// 把文本按第一个命中位置拆成三段，供界面高亮。
+    fn highlight(&self, item: &BackLogItem) -> BackLogItem {
+        let mut item = item.clone();
+        if let Some(start) = (!self.query.is_empty())
+            .then(|| item.back.find(self.query.as_str()))
+            .flatten()
+        {
+            let end = start + self.query.len();
+            item.pre = item.back[..start].to_shared_string();
+            item.hit = item.back[start..end].to_shared_string();
+            item.post = item.back[end..].to_shared_string();
+        }
+        item
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct Script {
@@ -89,7 +167,9 @@ pub(crate) struct Script {
     explain: String,
     backlog_offset: usize,
     backlog: Vec<BackLogItem>,
+    backlog_filter: BacklogFilter,
     commands: Vec<Commands>,
+    hashes: Vec<u64>,
     current_block: usize,
     current_bgm: String,
     pre_voice: Option<(SharedString, SharedString)>,
@@ -107,7 +187,9 @@ impl Script {
             explain: String::new(),
             backlog_offset: 0,
             backlog: Vec::new(),
+            backlog_filter: BacklogFilter::default(),
             commands: Vec::new(),
+            hashes: Vec::new(),
             current_block: 0,
             current_bgm: String::new(),
             pre_voice: None,
@@ -130,22 +212,91 @@ impl Script {
     }
 
     pub(crate) fn set_explain(&mut self, explain: &str) {
-        let mut explain = explain;
-        if explain.len() > 18 {
-            explain = &explain[0..18];
+        // 退到字符边界再截断，直接按字节截可能切在多字节字符中间
+        let mut end = explain.len().min(EXPLAIN_MAX_BYTES);
+        while !explain.is_char_boundary(end) {
+            end -= 1;
         }
-        self.explain = format!("{}{}", explain, "...");
+        self.explain = format!("{}{}", &explain[..end], "...");
     }
 
-    pub(crate) fn set_offset(&mut self, offset: i32) {
+    pub(crate) fn set_offset(&mut self, offset: i32, page_size: usize) {
         let new_offset = (self.backlog_offset as i32 + offset).max(0);
         // 不能超过最大可偏移量
-        let max_offset = self.max_offset();
+        let max_offset = self.max_offset(page_size);
         self.backlog_offset = new_offset.min(max_offset as i32) as usize;
     }
 
-    fn max_offset(&self) -> usize {
-        self.backlog.len().saturating_sub(WINDOW_SIZE)
+    fn max_offset(&self, page_size: usize) -> usize {
+        self.filtered_backlog().len().saturating_sub(page_size)
+    }
+
+    pub(crate) fn backlog_top(&mut self, page_size: usize) {
+        self.backlog_offset = self.max_offset(page_size);
+    }
+
+    pub(crate) fn backlog_bottom(&mut self) {
+        self.backlog_offset = 0;
+    }
+
+    /// 设置检索条件并回到最新一页，条件都为空时显示全部履历。
+    pub(crate) fn set_backlog_filter(&mut self, query: &str, speaker: Option<(String, String)>) {
+        self.backlog_filter = BacklogFilter {
+            query: query.to_string(),
+            speaker,
+        };
+        self.backlog_offset = 0;
+    }
+
+    /// 检索后的履历，同一 NVL 页的台词合成一条。
+    fn filtered_backlog(&self) -> Vec<BackLogItem> {
+        let mut items: Vec<BackLogItem> = Vec::new();
+        for item in self
+            .backlog
+            .iter()
+            .filter(|item| self.backlog_filter.matches(item))
+        {
+            let line = if item.front.is_empty() {
+                item.back.to_string()
+            } else {
+                format!("{}：{}", item.front, item.back)
+            };
+            match items.last_mut() {
+                Some(last) if item.page != 0 && last.page == item.page => {
+                    last.back = format!("{}\n{line}", last.back).to_shared_string();
+                    last.lines += 1;
+                    if !item.voice.is_empty() {
+                        last.chara = item.chara.clone();
+                        last.voice = item.voice.clone();
+                    }
+                }
+                _ if item.page != 0 => items.push(BackLogItem {
+                    front: SharedString::new(),
+                    back: line.to_shared_string(),
+                    lines: 1,
+                    ..item.clone()
+                }),
+                _ => items.push(item.clone()),
+            }
+        }
+        items
+    }
+
+    /// 回滚时直接恢复位置、履历长度、BGM 和存档说明。
+    pub(crate) fn restore(
+        &mut self,
+        index: usize,
+        backlog_len: usize,
+        bgm: String,
+        explain: String,
+    ) {
+        self.current_block = index;
+        self.backlog.truncate(backlog_len);
+        self.backlog_offset = 0;
+        self.current_bgm = bgm;
+        self.explain = explain;
+        self.pre_items = PreItems::default();
+        self.pre_voice = None;
     }
 
     pub(crate) fn set_current_bgm(&mut self, bgm: String) {
@@ -176,6 +327,7 @@ impl Script {
 
     pub(crate) fn set_backlog(&mut self, backlog: Vec<BackLogItem>) {
         self.backlog = backlog;
+        self.backlog_offset = 0;
     }
 
     pub(crate) fn insert_background(&mut self, index: usize, command: Command) {
@@ -186,6 +338,14 @@ impl Script {
         self.timeline.insert_bgm(index, bgm);
     }
 
+    pub(crate) fn insert_nvl(&mut self, index: usize, on: bool) {
+        self.timeline.insert_nvl(index, on);
+    }
+
+    pub(crate) fn insert_textbox(&mut self, index: usize, state: TextboxState) {
+        self.timeline.insert_textbox(index, state);
+    }
+
     pub(crate) fn insert_choice(&mut self, choice: String, label: Label) {
         self.choices.insert(choice, label);
     }
@@ -212,26 +372,65 @@ impl Script {
             index: self.current_block as i32,
             chara,
             voice,
+            ..Default::default()
         });
     }
 
-    pub(crate) fn push_command(&mut self, command: Commands) {
-        self.commands.push(command);
+    /// NVL 模式下的台词，履历界面里按页合并显示。
+    pub(crate) fn push_nvl_backlog(
+        &mut self,
+        name: SharedString,
+        text: SharedString,
+        voice: Option<(SharedString, SharedString)>,
+        page: i32,
+    ) {
+        self.push_backlog(name, text, voice);
+        if let Some(item) = self.backlog.last_mut() {
+            item.page = page;
+        }
     }
 
-    pub(crate) fn backlog(&self) -> Vec<BackLogItem> {
-        let total = self.backlog.len();
-        if total == 0 {
-            return vec![];
-        }
+    /// 新 NVL 页的编号，和履历里已有的页不重复。
+    pub(crate) fn next_nvl_page(&self) -> i32 {
+        self.backlog.iter().map(|item| item.page).max().unwrap_or(0) + 1
+    }
 
-        let end = total.saturating_sub(self.backlog_offset);
-        let start = end.saturating_sub(WINDOW_SIZE);
+    pub(crate) fn push_command(&mut self, command: Commands, hash: u64) {
+        self.commands.push(command);
+        self.hashes.push(hash);
+    }
+
+    pub(crate) fn backlog(&self, page_size: usize) -> Vec<BackLogItem> {
+        let backlog = self.filtered_backlog();
+        let end = backlog.len().saturating_sub(self.backlog_offset);
+        let start = end.saturating_sub(page_size);
+        backlog[start..end]
+            .iter()
+            .map(|item| self.backlog_filter.highlight(item))
+            .collect()
+    }
+
+    /// 存档用的履历：去掉当前块的条目（读档时会重新执行），只保留最后 limit 条。
+    pub(crate) fn saved_backlog(&self, limit: usize) -> Vec<BackLogItem> {
+        let end = self
+            .backlog
+            .iter()
+            .rposition(|item| item.script != self.name || item.index as usize != self.current_block)
+            .map_or(0, |index| index + 1);
+        let start = end.saturating_sub(limit);
         self.backlog[start..end].to_vec()
     }
 
+    pub(crate) fn full_backlog(&self) -> &[BackLogItem] {
+        &self.backlog
+    }
+
+    pub(crate) fn backlog_len(&self) -> usize {
+        self.backlog.len()
+    }
+
     pub(crate) fn last_voice(&self) -> Option<(String, String)> {
-        let backlog = self.backlog.last().unwrap();
+        let backlog = self.backlog.last()?;
         if backlog.voice.is_empty() && backlog.chara.is_empty() {
             return None;
         }
@@ -262,16 +461,77 @@ impl Script {
         self.pre_voice.take()
     }
 
-    pub(crate) fn pre_items(&mut self) -> (Option<Command>, PreBgm, Option<Figure>) {
-        let pre_items = self.pre_items.clone();
-        self.pre_items = PreItems::default();
-        (pre_items.pre_bg, pre_items.pre_bgm, pre_items.pre_figures)
+    pub(crate) fn pre_items(&mut self) -> PreItems {
+        std::mem::take(&mut self.pre_items)
     }
 
     pub(crate) fn find_label(&self, name: &str) -> Option<&usize> {
         self.labels.get(name)
     }
 
+    /// 生成第 `block` 块的锚点，标签为空表示相对剧本开头。
+    pub(crate) fn anchor(&self, block: usize) -> Anchor {
+        let (label, base) = self
+            .labels
+            .iter()
+            .filter(|(_, &index)| index <= block)
+            .max_by_key(|(_, &index)| index)
+            .map(|(label, &index)| (label.clone(), index))
+            .unwrap_or_default();
+        Anchor {
+            label,
+            offset: block - base,
+            hash: self
+                .hashes
+                .get(block)
+                .map(|hash| format!("{hash:016x}"))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 在当前剧本中解析锚点，返回块号以及是否精确命中。
+    /// 优先使用标签 + 偏移，内容哈希不符时先在该标签的段落内、再在整个剧本中
+    /// 就近查找相同内容的块，都找不到时退回标签 + 偏移（或原块号）的近似位置。
+    /// 只有标签 + 偏移处的内容一致才算精确命中，挪动过的位置都会被报告。
+    pub(crate) fn resolve_anchor(&self, anchor: &Anchor, index: usize) -> (usize, bool) {
+        let Ok(hash) = u64::from_str_radix(&anchor.hash, 16) else {
+            // 旧存档没有锚点，只能沿用块号
+            return (index, true);
+        };
+
+        let base = if anchor.label.is_empty() {
+            Some(0)
+        } else {
+            self.labels.get(&anchor.label).copied()
+        };
+        let guess = base.map_or(index, |base| base + anchor.offset);
+        if self.hashes.get(guess) == Some(&hash) {
+            return (guess, true);
+        }
+
+        // 内容相同的块（如重复的“……”）可能不止一处，先限定在锚点标签的段落内
+        let section = base.map(|base| {
+            let end = self
+                .labels
+                .values()
+                .copied()
+                .filter(|&start| start > base)
+                .min()
+                .unwrap_or(self.hashes.len());
+            base..end
+        });
+        let nearest = |range: std::ops::Range<usize>| {
+            range
+                .filter(|&i| self.hashes.get(i) == Some(&hash))
+                .min_by_key(|i| i.abs_diff(guess))
+        };
+        let found = section
+            .and_then(nearest)
+            .or_else(|| nearest(0..self.hashes.len()));
+        let resolved = found.unwrap_or(guess.min(self.commands.len().saturating_sub(1)));
+        (resolved, false)
+    }
+
     pub(crate) fn get_choice_label(&self, name: &str) -> Option<&Label> {
         self.choices.get(name)
     }
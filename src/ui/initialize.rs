@@ -1,14 +1,114 @@
+use crate::config::{self, args::ARGS};
+#[cfg(feature = "control")]
+use crate::control;
 use crate::error::EngineError;
-use crate::executors::{executor::Executor, load_data};
+use crate::executors::{
+    crash,
+    executor::Executor,
+    load_data,
+    record::{self, Input},
+};
+use crate::logger;
 slint::include_modules!();
 
+/// 启动阶段的错误显示在对话框里，关闭后再把错误返回给 main。
+fn startup_error(error: EngineError) -> EngineError {
+    logger::log_error("startup", &error);
+    if let Ok(dialog) = ErrorDialog::new() {
+        dialog.set_message(logger::error_chain(&error).into());
+        dialog.on_quit({
+            let weak = dialog.as_weak();
+            move || {
+                if let Some(dialog) = weak.upgrade() {
+                    let _ = dialog.hide();
+                }
+            }
+        });
+        let _ = dialog.run();
+    }
+    error
+}
+
 pub(crate) async fn ui() -> Result<(), EngineError> {
+    config::load_all()
+        .map_err(EngineError::from)
+        .map_err(startup_error)?;
+    logger::attach_file();
+
     let window = MainWindow::new()?;
     let weak = window.as_weak();
 
-    let mut executor = Executor::new(weak)?;
+    let mut executor = Executor::new(weak).map_err(startup_error)?;
+
+    let executor_tx = load_data(&mut executor).map_err(startup_error)?;
+    crash::install(&executor);
+    if let Some(port) = ARGS.control() {
+        #[cfg(feature = "control")]
+        control::start(&executor, port);
+        #[cfg(not(feature = "control"))]
+        log::warn!("--control {port} ignored: built without the `control` feature");
+    }
+
+    window.on_new_game({
+        let mut executor = executor.clone();
+        move || {
+            record::record_input(Input::NewGame);
+            if let Err(e) = executor.execute_new_game() {
+                executor.report_error("开始游戏", e);
+            }
+        }
+    });
+
+    window.on_debug_refresh({
+        let executor = executor.clone();
+        move || {
+            if let Some(window) = executor.get_weak().upgrade() {
+                window.set_debug_info(executor.debug_info().into());
+            }
+        }
+    });
+
+    window.on_debug_command({
+        let mut executor = executor.clone();
+        move |line| {
+            let output = executor.execute_console(&line);
+            if let Some(window) = executor.get_weak().upgrade() {
+                window.set_debug_output(output.into());
+            }
+        }
+    });
 
-    let executor_tx = load_data(&mut executor)?;
+    window.on_recover({
+        let mut executor = executor.clone();
+        move || {
+            if let Err(e) = executor.execute_recover() {
+                executor.report_error("恢复存档", e);
+            }
+        }
+    });
+
+    window.on_discard_recovery({
+        let executor = executor.clone();
+        move || {
+            if let Err(e) = executor.execute_discard_recovery() {
+                executor.report_error("删除恢复存档", e);
+            }
+        }
+    });
+
+    window.on_splash_skip({
+        let executor = executor.clone();
+        move || {
+            record::record_input(Input::SplashSkip);
+            let mut executor = executor.clone();
+            slint::spawn_local(async move {
+                if let Err(e) = executor.execute_splash_skip().await {
+                    executor.report_error("跳过", e);
+                }
+            })
+            .expect("splash skip: no slint event loop");
+        }
+    });
 
     let mut is_fullscreen = false;
     let weak_for_fullscreen = executor.get_weak();
@@ -28,117 +128,225 @@ pub(crate) async fn ui() -> Result<(), EngineError> {
     window.on_save({
         let mut executor = executor.clone();
         move |index, page_num| {
-            executor
-                .execute_save(index, page_num)
-                .expect("Save panicked");
+            record::record_input(Input::Save {
+                page: page_num,
+                index,
+            });
+            if let Err(e) = executor.execute_save(index, page_num) {
+                executor.report_error("存档", e);
+            }
+        }
+    });
+
+    window.on_delete_save({
+        let executor = executor.clone();
+        move |index, page_num| {
+            if let Err(e) = executor.execute_delete_save(index, page_num) {
+                executor.report_error("删除存档", e);
+            }
+        }
+    });
+
+    window.on_copy_save({
+        let executor = executor.clone();
+        move |from_index, from_page, to_index, to_page| {
+            if let Err(e) = executor.execute_copy_save(from_index, from_page, to_index, to_page) {
+                executor.report_error("复制存档", e);
+            }
+        }
+    });
+
+    window.on_move_save({
+        let executor = executor.clone();
+        move |from_index, from_page, to_index, to_page| {
+            if let Err(e) = executor.execute_move_save(from_index, from_page, to_index, to_page) {
+                executor.report_error("移动存档", e);
+            }
+        }
+    });
+
+    window.on_lock_save({
+        let executor = executor.clone();
+        move |index, page_num| {
+            if let Err(e) = executor.execute_lock_save(index, page_num) {
+                executor.report_error("锁定存档", e);
+            }
         }
     });
 
     window.on_load({
         let mut executor = executor.clone();
-        move |name, index| {
-            executor
-                .execute_load(name.to_string(), index)
-                .expect("Load panicked");
+        move |index, page_num| {
+            record::record_input(Input::Load {
+                page: page_num,
+                index,
+            });
+            if let Err(e) = executor.execute_load_slot(index, page_num) {
+                executor.report_error("读档", e);
+            }
         }
     });
 
     window.on_get_ex({
         let executor = executor.clone();
         move || {
-            executor.execute_get_ex().expect("Get Ex panicked");
+            if let Err(e) = executor.execute_get_ex() {
+                executor.report_error("读取鉴赏", e);
+            }
         }
     });
 
     window.on_volume_changed({
         let mut executor = executor.clone();
         move || {
-            executor
-                .execute_bgm_volume()
-                .expect("bgm_volume change panicked");
-            executor
-                .execute_voice_volume()
-                .expect("voice_volume change panicked");
+            if let Err(e) = executor.execute_bgm_volume() {
+                executor.report_error("调整音量", e);
+            }
+            if let Err(e) = executor.execute_voice_volume() {
+                executor.report_error("调整音量", e);
+            }
         }
     });
 
     window.on_bgm_volume_changed({
         let mut executor = executor.clone();
         move || {
-            executor
-                .execute_bgm_volume()
-                .expect("Bgm volume change panicked");
+            if let Err(e) = executor.execute_bgm_volume() {
+                executor.report_error("调整音量", e);
+            }
         }
     });
 
     window.on_voice_volume_changed({
         let mut executor = executor.clone();
         move || {
-            executor
-                .execute_voice_volume()
-                .expect("Voice volume change panicked");
+            if let Err(e) = executor.execute_voice_volume() {
+                executor.report_error("调整音量", e);
+            }
         }
     });
 
     window.on_save_config({
         let executor = executor.clone();
         move || {
-            executor.execute_save_config().expect("Choose panicked");
+            if let Err(e) = executor.execute_save_config() {
+                executor.report_error("保存设置", e);
+            }
         }
     });
 
     window.on_choose({
         let mut executor = executor.clone();
-        move |choice| {
-            executor.execute_choose(choice).expect("Choose panicked");
+        move |index, choice| {
+            record::record_input(Input::Choose(index as usize));
+            if let Err(e) = executor.execute_choose(choice) {
+                executor.report_error("选择", e);
+            }
         }
     });
 
     window.on_backlog({
         let executor = executor.clone();
         move || {
-            executor.execute_backlog().expect("Backlog panicked");
+            if let Err(e) = executor.execute_backlog_open() {
+                executor.report_error("打开履历", e);
+            }
+        }
+    });
+
+    window.on_rollback({
+        let mut executor = executor.clone();
+        move || {
+            record::record_input(Input::Rollback);
+            if let Err(e) = executor.execute_rollback() {
+                executor.report_error("回滚", e);
+            }
         }
     });
 
     window.on_backlog_change({
         let mut executor = executor.clone();
         move |i| {
-            executor
-                .execute_backlog_change(i)
-                .expect("Backlog change panicked");
+            if let Err(e) = executor.execute_backlog_change(i) {
+                executor.report_error("翻页", e);
+            }
+        }
+    });
+
+    window.on_backlog_search({
+        let mut executor = executor.clone();
+        move |query, speaker| {
+            if let Err(e) = executor.execute_backlog_search(query.to_string(), speaker) {
+                executor.report_error("检索履历", e);
+            }
+        }
+    });
+
+    window.on_backlog_top({
+        let mut executor = executor.clone();
+        move || {
+            if let Err(e) = executor.execute_backlog_top() {
+                executor.report_error("跳转履历", e);
+            }
+        }
+    });
+
+    window.on_backlog_bottom({
+        let mut executor = executor.clone();
+        move || {
+            if let Err(e) = executor.execute_backlog_bottom() {
+                executor.report_error("跳转履历", e);
+            }
+        }
+    });
+
+    window.on_backlog_export({
+        let executor = executor.clone();
+        move |format| {
+            if let Err(e) = executor.execute_export_transcript(format) {
+                executor.report_error("导出", e);
+            }
         }
     });
 
     window.on_backlog_jump({
         let mut executor = executor.clone();
         move |name, i| {
-            executor
-                .execute_backlog_jump(name.to_string(), i)
-                .expect("Backlog jump panicked");
+            record::record_input(Input::BacklogJump {
+                script: name.to_string(),
+                index: i,
+            });
+            if let Err(e) = executor.execute_backlog_jump(name.to_string(), i) {
+                executor.report_error("跳转", e);
+            }
         }
     });
 
     window.on_backlog_replay({
         let executor = executor.clone();
         move |name, voice| {
-            executor
-                .play_voice(&name.to_string(), &voice.to_string())
-                .expect("Backlog resume panicked");
+            if let Err(e) = executor.play_voice(&name.to_string(), &voice.to_string()) {
+                executor.report_error("播放语音", e);
+            }
         }
     });
 
     window.on_replay_voice({
         let mut executor = executor.clone();
         move || {
-            executor.execute_replay().expect("Backlog resume panicked");
+            if let Err(e) = executor.execute_replay() {
+                executor.report_error("重播语音", e);
+            }
         }
     });
 
     window.on_clicked({
         let mut executor = executor.clone();
         move || {
-            executor.execute_script().expect("Clicked panicked");
+            record::record_input(Input::Click);
+            if let Err(e) = executor.execute_script() {
+                executor.report_error("执行剧本", e);
+            }
         }
     });
 
@@ -146,10 +354,15 @@ pub(crate) async fn ui() -> Result<(), EngineError> {
         let mut executor = executor.clone();
         let tx = executor_tx.auto_tx();
         move |source| {
+            let on = executor
+                .get_weak()
+                .upgrade()
+                .is_some_and(|w| w.get_is_auto());
+            record::record_input(Input::Auto { source, on });
             let tx = tx.clone();
-            executor
-                .execute_auto(tx, source)
-                .expect("TODO: panic message");
+            if let Err(e) = executor.execute_auto(tx, source) {
+                executor.report_error("自动播放", e);
+            }
         }
     });
 
@@ -157,26 +370,48 @@ pub(crate) async fn ui() -> Result<(), EngineError> {
         let mut executor = executor.clone();
         let tx = executor_tx.skip_tx();
         move |source| {
+            let on = executor
+                .get_weak()
+                .upgrade()
+                .is_some_and(|w| w.get_is_skip());
+            record::record_input(Input::Skip { source, on });
             let tx = tx.clone();
-            executor
-                .execute_skip(tx, source)
-                .expect("TODO: panic message");
+            if let Err(e) = executor.execute_skip(tx, source) {
+                executor.report_error("快进", e);
+            }
+        }
+    });
+
+    window.on_fast_forward({
+        let mut executor = executor.clone();
+        let tx = executor_tx.hold_tx();
+        move |hold| {
+            record::record_input(Input::FastForward(hold));
+            if let Err(e) = executor.execute_fast_forward(tx.clone(), hold) {
+                executor.report_error("快进", e);
+            }
         }
     });
 
     window.on_stop_video({
         let executor = executor.clone();
         move || {
+            record::record_input(Input::StopVideo);
             let executor = executor.clone();
             slint::spawn_local(async move {
                 if let Err(e) = executor.execute_stop_video().await {
-                    eprintln!("stop_video failed: {e}");
+                    executor.report_error("停止视频", e);
                 }
             })
             .expect("stop_video panicked");
         }
     });
 
+    window.on_dialogue_relayout({
+        let executor = executor.clone();
+        move || executor.relayout_dialogue()
+    });
+
     window.on_exit({
         let weak = executor.get_weak();
         move || {
@@ -193,6 +428,8 @@ pub(crate) async fn ui() -> Result<(), EngineError> {
         }
     });
 
+    executor.execute_start_title()?;
     window.run()?;
-    Ok(())
+    executor.save_read();
+    record::finish()
 }